@@ -0,0 +1,51 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use thesis_interpreter::parser::{LexicalParser, SrcInfo, SyntacticParser};
+use thesis_interpreter::share;
+
+/// Many top-level definitions of moderate depth.
+fn large(forms: usize) -> String {
+    (0..forms).map(|i| format!(
+        "($define! f{i} ($lambda (x . rest) ($if (<? x {i}) [x 'rest] (f{i} (- x 1) #t #\\a \"s\"))))\n"
+    )).collect()
+}
+
+/// A single form nested `depth` levels deep, with a datum at every level.
+fn nested(depth: usize) -> String {
+    format!("{}{}", "(a ".repeat(depth), ")".repeat(depth))
+}
+
+fn parse(text: &str) {
+    let mut parser = SyntacticParser::new(share!(SrcInfo::new("bench", text)));
+    parser.try_parse().unwrap();
+    criterion::black_box(parser.tree());
+}
+
+fn bench_inputs(c: &mut Criterion, name: &str, inputs: Vec<(usize, String)>) {
+    let mut group = c.benchmark_group(name);
+    for (size, text) in &inputs {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::new("try_parse", size), text, |b, text| b.iter(|| parse(text)));
+        group.bench_with_input(BenchmarkId::new("parse_untraced", size), text, |b, text| {
+            let mut lexer = LexicalParser::new();
+            lexer.parse_str(text);
+            let tokens = lexer.results();
+            b.iter(|| {
+                let mut parser = SyntacticParser::new(share!(SrcInfo::new("bench", "")));
+                parser.parse_untraced(tokens.clone());
+                criterion::black_box(parser.tree());
+            })
+        });
+    }
+    group.finish();
+}
+
+// The time per byte should stay flat as the inputs grow, in both width
+// and depth.
+fn bench_parser(c: &mut Criterion) {
+    bench_inputs(c, "large", [100, 1_000, 10_000].map(|forms| (forms, large(forms))).into());
+    bench_inputs(c, "nested", [100, 1_000, 5_000].map(|depth| (depth, nested(depth))).into());
+}
+
+criterion_group!(benches, bench_parser);
+criterion_main!(benches);
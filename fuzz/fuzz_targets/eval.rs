@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use thesis_interpreter::interpreter::eval_source;
+
+// Any text is read and evaluated within a bounded number of steps, and the
+// results are written. Errors are expected, only panics are reported.
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else { return };
+    for term in eval_source("fuzz", text, 10_000).unwrap_or_default() {
+        let _ = term.to_string();
+    }
+});
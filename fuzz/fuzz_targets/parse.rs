@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use thesis_interpreter::parser::{InfixTransformer, SrcInfo, SyntacticParser};
+use thesis_interpreter::share;
+
+// Any text is read into a tree, which is transformed and printed. Syntax
+// errors are expected, only panics are reported.
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else { return };
+    let src = share!(SrcInfo::new("fuzz", text));
+    let mut parser = SyntacticParser::new(src.clone());
+    let _ = parser.try_parse();
+    let tree = parser.tree();
+    let _ = tree.to_string();
+    let _ = InfixTransformer::default().transform(&src.borrow(), tree);
+});
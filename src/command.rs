@@ -8,6 +8,8 @@ pub struct Arg {
     optional: bool,
     /// Determine whether to stop parsing the rest args.
     interrupt: bool,
+    /// Determine whether the positional arg takes all the rest parameters.
+    variadic: bool,
     parameterized: Parameter,
     prefix: char,
     info: (&'static str, &'static str), // (Description, Details)
@@ -19,6 +21,7 @@ impl Arg {
             id: (id, '\0'),
             optional: false,
             interrupt: false,
+            variadic: false,
             parameterized: Parameter::No,
             prefix: '\0',
             info: ("", ""),
@@ -38,9 +41,8 @@ impl Arg {
     pub fn parameterize(mut self, parameterized: Parameter) -> Self {
         self.parameterized = parameterized;
         if self.prefix == '\0' {
-            match parameterized {
-                Parameter::Optional(_) => self.optional = true,
-                _ => {}
+            if let Parameter::Optional(_) = parameterized {
+                self.optional = true
             }
         }
         self
@@ -69,18 +71,28 @@ impl Arg {
         seq!(self.info.1 = content, self)
     }
 
+    /// Let the last positional arg take all the rest parameters, which are
+    /// joined by '\0' since it can't appear in an argument.
+    pub fn variadic(mut self) -> Self {
+        if self.prefix == '\0' {
+            seq!(self.variadic = true, self)
+        } else {
+            panic!("Error: Only a positional arg can be variadic.")
+        }
+    }
+
     pub fn interrupt(mut self) -> Self {
         seq!(self.interrupt = true, self)
     }
 
-    pub fn try_get_parameter(&self, parameter: Option<&String>) -> String {
+    pub fn try_get_parameter(&self, parameter: Option<&String>) -> Result<String, String> {
         use Parameter::*;
         match self.parameterized {
-            No => "".into(),
-            Optional(default) => parameter.unwrap_or(&default.to_string()).clone(),
+            No => Ok("".into()),
+            Optional(default) => Ok(parameter.unwrap_or(&default.to_string()).clone()),
             Required => parameter
-                .unwrap_or_else(|| panic!("Error: Parameter not found."))
-                .clone(),
+                .cloned()
+                .ok_or_else(|| format!("Error: Parameter of '{}' not found.", self.id.0)),
         }
     }
 
@@ -94,7 +106,7 @@ impl Arg {
             }
         };
         let info_0 = self.info.0;
-        let info_1 = if self.info.1 == "" { "".into() } else {
+        let info_1 = if self.info.1.is_empty() { "".into() } else {
             "\n      ".to_owned() + self.info.1
         };
         format!(
@@ -153,9 +165,12 @@ impl Command {
                 self.args.insert(format!("-{}", arg.id.1), arg);
             }
         } else {
-            if arg.optional == true && self.pos_args.last().is_some_and(|arg| !arg.optional) {
+            if arg.optional && self.pos_args.last().is_some_and(|arg| !arg.optional) {
                 panic!("Error: Cannot add a optional argument after a required one.")
             }
+            if self.pos_args.last().is_some_and(|arg| arg.variadic) {
+                panic!("Error: Cannot add an argument after a variadic one.")
+            }
             self.pos_args.push(arg);
         }
     }
@@ -166,35 +181,35 @@ impl Command {
         let mut results: HashMap<String, String> = HashMap::new();
         for (i, val) in args.iter().enumerate() {
             if expect_flag == 1 || expect_flag == 2 {
-                seq!(expect_flag = 0, break)
+                seq!(expect_flag = 0, continue)
             };
             match self.args.get(val) {
                 Some(arg) => {
-                    if !results.contains_key(&arg.id.0[1..]) {
+                    if !results.contains_key(&arg.id.0[2..]) {
                         // Note: The key for insertion has no "--".
                         results.insert(
                             arg.id.0[2..].to_string(),
-                            arg.try_get_parameter(args.get(i + 1)),
+                            arg.try_get_parameter(args.get(i + 1))?,
                         );
                         if_or!(arg.interrupt, return Ok(results));
                         expect_flag = arg.parameterized.into();
                         continue;
                     } else {
-                        panic!("Error: Duplicate parameter of '{}' was found.", arg.id.0)
+                        return Err(format!("Error: Duplicate parameter of '{}' was found.", arg.id.0))
                     }
                 }
                 None => pos_parameters.push(val.clone()),
             }
         }
         if expect_flag == 2 {
-            panic!("Error: Required parameter not found.")
+            return Err("Error: Required parameter not found.".to_string())
         }
         let pos_param_len = pos_parameters.len();
         let mut used_pos_arg = 0usize;
         let mut required_pos_arg = 0usize;
         let mut required_arg_id = "";
-        if pos_param_len > self.pos_args.len() {
-            panic!("Error: Too many parameters received.")
+        if pos_param_len > self.pos_args.len() && !self.pos_args.last().is_some_and(|arg| arg.variadic) {
+            return Err("Error: Too many parameters received.".to_string())
         }
 
         for arg in &self.pos_args {
@@ -206,6 +221,11 @@ impl Command {
             if used_pos_arg >= pos_param_len {
                 continue;
             }
+            if arg.variadic {
+                results.insert(arg.id.0.into(), pos_parameters[used_pos_arg..].join("\0"));
+                used_pos_arg = pos_param_len;
+                continue;
+            }
             results.insert(
                 arg.id.0.into(),
                 core::mem::take(&mut pos_parameters[used_pos_arg]),
@@ -227,7 +247,8 @@ impl Command {
             string.reserve(self.pos_args.len() * 3);
             for arg in &self.pos_args {
                 let bracket = if_or!(arg.optional, ('[', ']'), ('<', '>'));
-                string += format!(" {}{}{} ", bracket.0, arg.id.0, bracket.1).as_str();
+                let ellipsis = if_or!(arg.variadic, "...", "");
+                string += format!(" {}{}{ellipsis}{} ", bracket.0, arg.id.0, bracket.1).as_str();
             }
             string
         };
@@ -284,4 +305,27 @@ mod tests {
         command.add_arg(Arg::new("script"));
         assert_eq!(command.match_with(vec![]).unwrap_err(), "Error: Required argument 'script' was not found.");
     }
+
+    #[test]
+    fn command_match_with_errors() {
+        let mut command = Command::new("cli-test", "");
+        command.add_arg(Arg::new("--output").short_id('o').parameterize(Required));
+        command.add_arg(Arg::new("script").parameterize(Optional("-")));
+        assert_eq!(command.match_with(vec!["--output".into()]).unwrap_err(), "Error: Parameter of '--output' not found.");
+        assert_eq!(command.match_with(vec!["-o".into(), "a".into(), "--output".into(), "b".into()]).unwrap_err(),
+            "Error: Duplicate parameter of '--output' was found.");
+        assert_eq!(command.match_with(vec!["a".into(), "b".into()]).unwrap_err(), "Error: Too many parameters received.");
+    }
+
+    #[test]
+    fn command_match_variadic() {
+        use std::collections::HashMap;
+
+        let mut command = Command::new("cli-test", "");
+        command.add_arg(Arg::new("--check"));
+        command.add_arg(Arg::new("paths").variadic());
+        let map = command.match_with(vec!["a".into(), "--check".into(), "b".into(), "c".into()]).unwrap();
+        assert_eq!(map, HashMap::from([("check".into(), "".into()), ("paths".into(), "a\0b\0c".into())]));
+        assert_eq!(command.match_with(vec![]).unwrap_err(), "Error: Required argument 'paths' was not found.");
+    }
 }
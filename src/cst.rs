@@ -0,0 +1,378 @@
+use std::fmt::Display;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::{if_or, seq};
+use crate::error::Error;
+use crate::parser::{Delimiter, LexicalParser, Span, SrcInfo, SyntacticParser, Token, TokenKind};
+use crate::syntax::{Node, NodeValue};
+
+/// The kind of a node in the concrete syntax tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CstKind {
+    /// The whole source.
+    Root,
+    /// A sequence from its opening delimiter to the closing one, which is
+    /// missing if the sequence is never closed.
+    Sequence(Delimiter),
+    /// A quote prefix or a datum comment followed by the datum it applies
+    /// to, the trivia between them are kept inside.
+    Prefixed,
+    /// The tokens which can't be a part of any datum, which are closing
+    /// delimiters without opening ones and unterminated string literals.
+    Error
+}
+
+/// A child of a node in the concrete syntax tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstElement {
+    Node(CstNode),
+    Token(Token)
+}
+
+/// A node of the lossless concrete syntax tree, every byte of the source
+/// belongs to a token or trivia in the tree. The texts of its tokens in
+/// order are exactly the source, even if the source is malformed.
+///
+/// The trivia between the data belong to the innermost node being read
+/// there, those after the last datum of a sequence are placed before its
+/// closing delimiter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstNode {
+    kind: CstKind,
+    children: Vec<CstElement>
+}
+
+impl CstNode {
+    fn new(kind: CstKind, first: Token) -> Self {
+        Self { kind, children: vec![CstElement::Token(first)] }
+    }
+
+    /// Read the source into the tree, which is never failed. The errors
+    /// are reported by [`SyntacticParser::try_parse`], or along with the
+    /// tree by [`Self::try_parse`].
+    pub fn parse(src: &SrcInfo) -> Self {
+        let mut lexer = LexicalParser::new();
+        lexer.parse_str(&src.text);
+        Self::read(lexer)
+    }
+
+    /// Read the source into the tree, which fails with the errors reported
+    /// by [`SyntacticParser::try_parse`]. The tree is read from the same
+    /// tokens as checked.
+    pub fn try_parse(src: &SrcInfo) -> Result<Self, Vec<Error>> {
+        let mut lexer = LexicalParser::new();
+        lexer.parse_str(&src.text);
+        let (_, errors) = SyntacticParser::read_lexed(src, &lexer);
+        if_or!(errors.is_empty(), Ok(Self::read(lexer)), Err(errors))
+    }
+
+    fn read(lexer: LexicalParser) -> Self {
+        /// Close the innermost node into its parent.
+        fn close(stack: &mut Vec<CstNode>) {
+            if stack.len() < 2 { return }
+            let node = stack.pop().unwrap();
+            stack.last_mut().unwrap().children.push(CstElement::Node(node));
+        }
+        fn push(stack: &mut [CstNode], element: CstElement) {
+            stack.last_mut().unwrap().children.push(element)
+        }
+        let unterminated = lexer.unterminated_string().map(|pos| pos.byte());
+        // The nodes being read, from the root.
+        let mut stack = vec![Self { kind: CstKind::Root, children: vec![] }];
+        for token in lexer.results() {
+            match token.kind() {
+                TokenKind::Open(delimiter) => seq!(stack.push(Self::new(CstKind::Sequence(delimiter), token)), continue),
+                TokenKind::Quote(_) | TokenKind::DatumComment => seq!(stack.push(Self::new(CstKind::Prefixed, token)), continue),
+                TokenKind::Close(delimiter) => {
+                    let Some(matched) = stack.iter().rposition(|node| node.kind == CstKind::Sequence(delimiter)) else {
+                        push(&mut stack, CstElement::Node(Self::new(CstKind::Error, token)));
+                        continue
+                    };
+                    // The nodes inside the matched sequence are left unclosed.
+                    while stack.len() > matched + 1 { close(&mut stack) }
+                    push(&mut stack, CstElement::Token(token));
+                    close(&mut stack);
+                },
+                TokenKind::String if unterminated == Some(token.start().byte()) =>
+                    seq!(push(&mut stack, CstElement::Node(Self::new(CstKind::Error, token))), continue),
+                TokenKind::Comment | TokenKind::Whitespace => seq!(push(&mut stack, CstElement::Token(token)), continue),
+                TokenKind::Dot => {
+                    // A dot can't be commented out.
+                    if stack.last().is_some_and(Self::is_datum_comment) { close(&mut stack) }
+                    seq!(push(&mut stack, CstElement::Token(token)), continue)
+                },
+                _ => push(&mut stack, CstElement::Token(token))
+            }
+            // A completed datum closes the pending prefixes, until a datum
+            // comment is closed.
+            while let Some(last) = stack.last().filter(|node| node.kind == CstKind::Prefixed) {
+                let commented = last.is_datum_comment();
+                close(&mut stack);
+                if commented { break }
+            }
+        }
+        while stack.len() > 1 { close(&mut stack) }
+        stack.pop().unwrap()
+    }
+
+    pub fn kind(&self) -> CstKind { self.kind }
+
+    pub fn children(&self) -> &[CstElement] { &self.children }
+
+    /// Whether the node is a datum comment along with the commented datum.
+    pub fn is_datum_comment(&self) -> bool {
+        self.kind == CstKind::Prefixed
+            && matches!(self.children.first(), Some(CstElement::Token(token)) if token.kind() == TokenKind::DatumComment)
+    }
+
+    /// All the tokens in the tree in order, including the trivia.
+    pub fn tokens(&self) -> Vec<&Token> {
+        let mut tokens = vec![];
+        let mut stack = vec![self.children.iter()];
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                Some(CstElement::Token(token)) => tokens.push(token),
+                Some(CstElement::Node(node)) => stack.push(node.children.iter()),
+                None => seq!(stack.pop(), ())
+            }
+        }
+        tokens
+    }
+
+    /// The byte range covered by the node, which is empty only if the node
+    /// is the root of an empty source.
+    pub fn range(&self) -> Range<usize> {
+        let tokens = self.tokens();
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => first.start().byte()..last.end().byte(),
+            _ => 0..0
+        }
+    }
+
+    /// Project the tree to the syntax tree read by the syntactic parser,
+    /// the trivia and datum comments are dropped.
+    ///
+    /// The projection equals the tree read by [`SyntacticParser::try_parse`]
+    /// from a source without syntax errors. Otherwise, the invalid atoms
+    /// are kept in the same way as the parser, the misplaced dots and the
+    /// extra data after the tail of a dotted list are dropped.
+    pub fn to_node(&self, src: &SrcInfo) -> Node {
+        /// A node being projected.
+        struct Frame<'a> {
+            node: &'a CstNode,
+            next: usize,
+            nodes: Vec<Node>,
+            /// The number of the data before the first dot.
+            dot: Option<usize>,
+            /// Where the first and the last non-trivia tokens are.
+            range: Option<Range<usize>>
+        }
+        impl<'a> Frame<'a> {
+            fn new(node: &'a CstNode) -> Self {
+                Self { node, next: 0, nodes: vec![], dot: None, range: None }
+            }
+            fn extend(&mut self, range: Range<usize>) {
+                self.range = Some(self.range.as_ref().map_or(range.start, |own| own.start)..range.end);
+            }
+            fn finish(mut self, id: &Rc<str>) -> Node {
+                let span = Span::new(id.clone(), self.range.clone().unwrap_or_default());
+                let value = match self.node.kind {
+                    CstKind::Sequence(Delimiter::Paren) => match self.dot {
+                        Some(dot) if dot > 0 && self.nodes.len() > dot => {
+                            self.nodes.truncate(dot + 1);
+                            let tail = self.nodes.pop().unwrap();
+                            NodeValue::DottedList(self.nodes, Box::new(tail))
+                        },
+                        _ => NodeValue::List(self.nodes)
+                    },
+                    CstKind::Sequence(delimiter) => return Node::delimited(delimiter, self.nodes).with_span(span),
+                    CstKind::Prefixed => {
+                        let Some(CstElement::Token(prefix)) = self.node.children.first() else { unreachable!() };
+                        let TokenKind::Quote(quote) = prefix.kind() else { unreachable!() };
+                        let symbol = Node::from(NodeValue::Symbol(quote.name().into()))
+                            .with_span(Span::new(id.clone(), prefix.range()));
+                        self.nodes.insert(0, symbol);
+                        NodeValue::List(self.nodes)
+                    },
+                    _ => NodeValue::List(self.nodes)
+                };
+                Node::from(value).with_span(span)
+            }
+        }
+        let id: Rc<str> = src.id.as_str().into();
+        let mut stack = vec![Frame::new(self)];
+        loop {
+            let frame = stack.last_mut().unwrap();
+            if let Some(child) = frame.node.children.get(frame.next) {
+                frame.next += 1;
+                match child {
+                    CstElement::Token(token) => {
+                        if token.kind().is_trivia() { continue }
+                        frame.extend(token.range());
+                        match token.kind() {
+                            TokenKind::Dot => seq!(frame.dot.get_or_insert(frame.nodes.len()), ()),
+                            TokenKind::Open(_) | TokenKind::Close(_) | TokenKind::Quote(_) | TokenKind::DatumComment => (),
+                            _ => {
+                                let (value, _) = SyntacticParser::read_atom(src, token);
+                                frame.nodes.push(Node::from(value).with_span(Span::new(id.clone(), token.range())))
+                            }
+                        }
+                    },
+                    CstElement::Node(node) if node.kind == CstKind::Error || node.is_datum_comment() => (),
+                    CstElement::Node(node) => stack.push(Frame::new(node))
+                }
+                continue
+            }
+            let frame = stack.pop().unwrap();
+            let range = frame.range.clone();
+            let node = frame.finish(&id);
+            match stack.last_mut() {
+                Some(parent) => {
+                    if let Some(range) = range { parent.extend(range) }
+                    parent.nodes.push(node)
+                },
+                None => return node.with_span(Span::new(id, 0..src.text.len()))
+            }
+        }
+    }
+}
+
+/// Write the source of the tree.
+impl Display for CstNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.tokens().into_iter().try_for_each(|token| f.write_str(token.text()))
+    }
+}
+
+/// Drop the nested nodes one by one, a deep tree could overflow the stack
+/// otherwise.
+impl Drop for CstNode {
+    fn drop(&mut self) {
+        let mut stack: Vec<CstNode> = vec![];
+        let mut children = core::mem::take(&mut self.children);
+        loop {
+            stack.extend(children.drain(..).filter_map(|child| match child {
+                CstElement::Node(node) => Some(node),
+                CstElement::Token(_) => None
+            }));
+            let Some(mut node) = stack.pop() else { break };
+            children = core::mem::take(&mut node.children);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::parser::{SrcInfo, SyntacticParser};
+    use crate::share;
+    use super::{CstElement, CstKind, CstNode};
+
+    fn parse(source: &str) -> (SrcInfo, CstNode) {
+        let src = SrcInfo::new("test", source);
+        let cst = CstNode::parse(&src);
+        (src, cst)
+    }
+
+    #[test]
+    fn cst_lossless() {
+        let sources = [
+            "",
+            " ; comment\n(a . b) #| block |# [c {d}]\r\n",
+            "'(a ,@b `c) #; (ignored) #;#;x y z",
+            "((a ]) b",
+            "))(\"unterminated\n",
+            "#| never closed",
+            "(a #\\x #\\space \"s\\n\" 1.5e3 #inert)"
+        ];
+        for source in sources {
+            let (_, cst) = parse(source);
+            assert_eq!(cst.kind(), CstKind::Root);
+            assert_eq!(cst.to_string(), source);
+            assert_eq!(cst.range(), 0..source.len());
+        }
+        let (_, cst) = parse("'  x ]");
+        let kinds: Vec<_> = cst.children().iter().map(|child| match child {
+            CstElement::Node(node) => Some(node.kind()),
+            CstElement::Token(_) => None
+        }).collect();
+        assert_eq!(kinds, [Some(CstKind::Prefixed), None, Some(CstKind::Error)]);
+        let CstElement::Node(quoted) = &cst.children()[0] else { unreachable!() };
+        assert_eq!(quoted.to_string(), "'  x");
+    }
+
+    #[test]
+    fn cst_projection() {
+        let sources = [
+            "(a . b) (c . (d e)) [x y] {1 + 2}",
+            "'a `(b ,c ,@d) '#;x y #;'z w",
+            "($define! f ($lambda (x) ; comment\n  (* x #| block |# 2)))",
+            "(#t #false #\\a \"s\\t\" 1/2 #ignore . #inert)",
+            ""
+        ];
+        for source in sources {
+            let (src, cst) = parse(source);
+            let mut parser = SyntacticParser::new(share!(SrcInfo::new("test", source)));
+            parser.try_parse().unwrap();
+            let (node, tree) = (cst.to_node(&src), parser.tree());
+            assert_eq!(node, tree);
+            assert_eq!(CstNode::try_parse(&src).unwrap(), cst);
+            assert_eq!(format!("{node:?}"), format!("{tree:?}"));
+        }
+        let (src, cst) = parse("(a . b c) (. d) [e . f] (g");
+        assert_eq!(cst.to_node(&src).to_source(), "(a . b)\n(d)\n[e f]\n(g)\n");
+        assert_eq!(CstNode::try_parse(&src).unwrap_err().len(), 4);
+    }
+
+    #[test]
+    fn cst_deep_nesting() {
+        let depth = 100_000;
+        let source = format!("{}{}", "('a ".repeat(depth), ")".repeat(depth));
+        let (src, cst) = parse(&source);
+        assert_eq!(cst.to_string(), source);
+        let node = cst.to_node(&src);
+        assert_eq!(node.span().unwrap().range(), 0..source.len());
+    }
+
+    /// Sources without syntax errors, whose data are separated by trivia.
+    fn readable_source() -> impl Strategy<Value = String> {
+        let trivia = prop::sample::select(vec![" ", "\n", " ; line\n", " #| block |# ", " #;(x y) "]);
+        let atom = prop::sample::select(vec!["a", "b1", "#t", "1/2", "\"s\\n\"", "#\\x", "#inert"])
+            .prop_map(str::to_string);
+        let datum = atom.prop_recursive(8, 64, 6, move |inner| {
+            let items = prop::collection::vec((inner.clone(), trivia.clone()), 0..6)
+                .prop_map(|items| items.into_iter().map(|(item, trivia)| item + trivia).collect::<String>());
+            prop_oneof![
+                (prop::sample::select(vec!["()", "[]", "{}"]), items.clone())
+                    .prop_map(|(delimiter, items)| format!("{}{items}{}", &delimiter[..1], &delimiter[1..])),
+                (items, inner.clone()).prop_map(|(items, tail)| format!("(x {items}. {tail})")),
+                (prop::sample::select(vec!["'", "`", ",", ",@", "#;y "]), inner)
+                    .prop_map(|(prefix, datum)| format!("{prefix}{datum}"))
+            ]
+        });
+        prop::collection::vec(datum, 0..4).prop_map(|data| data.join("\n"))
+    }
+
+    proptest! {
+        #[test]
+        fn cst_parse_arbitrary(source in "[()\\[\\]{} \n;#|'`,@.\\\\\"abc1]{0,40}") {
+            let (src, cst) = parse(&source);
+            prop_assert_eq!(cst.to_string(), source.clone());
+            let mut parser = SyntacticParser::new(share!(SrcInfo::new("test", source.as_str())));
+            if parser.try_parse().is_ok() {
+                prop_assert_eq!(cst.to_node(&src), parser.tree());
+            }
+        }
+
+        #[test]
+        fn cst_project_readable(source in readable_source()) {
+            let (src, cst) = parse(&source);
+            prop_assert_eq!(cst.to_string(), source.clone());
+            let mut parser = SyntacticParser::new(share!(SrcInfo::new("test", source.as_str())));
+            prop_assert!(parser.try_parse().is_ok());
+            prop_assert_eq!(cst.to_node(&src), parser.tree());
+        }
+    }
+}
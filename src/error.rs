@@ -1,15 +1,18 @@
 use std::process::exit;
 
-use ariadne::{Fmt, Label, Report, ReportBuilder, ReportKind, Source};
+use ariadne::{Config, Fmt, IndexType, Label, Report, ReportBuilder, ReportKind, Source};
 
 use crate::seq;
-use crate::parser::{SourcePos, SrcInfo};
+use crate::parser::{SourcePos, Span, SrcInfo};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorKind {
     InvalidSyntax,
     FreeIdentifier,
-    TypeMismatch
+    TypeMismatch,
+    InvalidArithmetic,
+    IoFailure,
+    RecursionLimit
 }
 
 impl ErrorKind {
@@ -17,30 +20,42 @@ impl ErrorKind {
         match &self {
             Self::InvalidSyntax => "E01",
             Self::FreeIdentifier => "E02",
-            Self::TypeMismatch => "E03"
+            Self::TypeMismatch => "E03",
+            Self::InvalidArithmetic => "E04",
+            Self::IoFailure => "E05",
+            Self::RecursionLimit => "E06"
         }
     }
 }
 
+pub type ReportSpan = (String, std::ops::Range<usize>);
+
 #[derive(Debug)]
 pub struct Error {
     kind: ErrorKind,
     message: String,
     span: std::ops::Range<usize>,
-    labels: Vec<Label<(String, std::ops::Range<usize>)>>,
-    pub(crate) report: Option<ReportBuilder<'static, (String, std::ops::Range<usize>)>>
+    labels: Vec<Label<ReportSpan>>,
+    /// The expression raising a runtime error.
+    location: Option<Span>,
+    pub(crate) report: Option<Box<ReportBuilder<'static, ReportSpan>>>
 }
 
 impl Error {
     pub fn new(kind: ErrorKind) -> Self {
-        Self { kind, message: "".to_string(), span: 0..0, labels: vec![], report: None }
+        Self { kind, message: "".to_string(), span: 0..0, labels: vec![], location: None, report: None }
     }
 
     pub fn kind(&self) -> ErrorKind { self.kind }
 
     pub fn message(&self) -> &String { &self.message }
 
-    pub fn with_label(mut self, label: Label<(String, std::ops::Range<usize>)>) -> Self {
+    /// The byte range of the source underlined by the report.
+    pub fn span(&self) -> std::ops::Range<usize> { self.span.clone() }
+
+    pub fn location(&self) -> Option<&Span> { self.location.as_ref() }
+
+    pub fn with_label(mut self, label: Label<ReportSpan>) -> Self {
         seq!(self.labels.push(label), self)
     }
 
@@ -52,13 +67,16 @@ impl Error {
         seq!(self.span = span, self)
     }
 
-    pub fn return_error(mut self, src: &SrcInfo, pos: SourcePos, label: String) -> Self {
-        // let kind = format!("{:?}", self.kind);
-        // To make it appear like rust-style error.
-        print!("{}", "error".fg(ariadne::Color::Red));
+    pub fn with_location(mut self, location: Span) -> Self {
+        seq!(self.location = Some(location), self)
+    }
 
-        let mut builder = 
-        Report::build(ReportKind::Custom("\x08", ariadne::Color::Red), &src.id, pos.i())
+    /// Build the report of the error at the position, the spans of the
+    /// error and its labels are byte ranges of the source.
+    fn build_report(&self, src: &SrcInfo, pos: SourcePos, label: String) -> ReportBuilder<'static, ReportSpan> {
+        let mut builder =
+        Report::build(ReportKind::Custom("\x08", ariadne::Color::Red), &src.id, pos.byte())
+            .with_config(Config::default().with_index_type(IndexType::Byte))
             .with_code(self.kind.to_error_code())
             .with_message(self.message())
             .with_label(
@@ -70,31 +88,41 @@ impl Error {
         for label in &self.labels {
             builder = builder.with_label(label.clone());
         }
+        builder
+    }
 
-        self.report = Some(builder);
+    pub fn return_error(mut self, src: &SrcInfo, pos: SourcePos, label: String) -> Self {
+        self.report = Some(Box::new(self.build_report(src, pos, label)));
         self
     }
 
+    /// Print the report built by `return_error`, an error raised without
+    /// a report is reported against its location, or the start of the
+    /// source if it has none.
+    pub fn print(self, src: &SrcInfo) {
+        let this = match &self.location {
+            _ if self.report.is_some() => self,
+            Some(location) if location.id() == src.id => {
+                let pos = SourcePos::locate(&src.text, location.start());
+                let span = location.range();
+                self.with_span(span).return_error(src, pos, "Raised while evaluating this expression.".to_string())
+            },
+            _ => self.return_error(src, SourcePos::new(), "".to_string())
+        };
+        // To make it appear like rust-style error.
+        print!("{}", "error".fg(ariadne::Color::Red));
+        this.report.unwrap()
+            .finish()
+            .print((src.id.clone(), Source::from(&src.text)))
+            .unwrap();
+    }
+
     pub fn report_error(self, src: &SrcInfo, pos: SourcePos, label: String) -> ! {
         // let kind = format!("{:?}", self.kind);
         // To make it appear like rust-style error.
         print!("{}", "error".fg(ariadne::Color::Red));
 
-        let mut builder = 
-        Report::build(ReportKind::Custom("\x08", ariadne::Color::Red), &src.id, pos.i())
-            .with_code(self.kind.to_error_code())
-            .with_message(self.message())
-            .with_label(
-                Label::new((src.id.clone(), self.span.clone()))
-                    .with_message(label)
-                    .with_color(ariadne::Color::Red)
-            );
-
-        for label in self.labels {
-            builder = builder.with_label(label);
-        }
-
-        builder
+        self.build_report(src, pos, label)
             .finish()
             .print((src.id.clone(), Source::from(&src.text)))
             .unwrap();
@@ -113,7 +141,7 @@ impl core::error::Error for Error {}
 
 impl<S: Into<String>> From<(ErrorKind, S)> for Error {
     fn from(value: (ErrorKind, S)) -> Self {
-        Self { kind: value.0, message: value.1.into(), span: 0..0, labels: vec![], report: None }
+        Self { kind: value.0, message: value.1.into(), span: 0..0, labels: vec![], location: None, report: None }
     }
 }
 
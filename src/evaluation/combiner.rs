@@ -1,14 +1,136 @@
 use std::fmt::Debug;
+use std::rc::Rc;
 
 use crate::error::Error;
-use super::term::Term;
-use super::context::Context;
+use super::term::{Term, TermValue};
+use super::context::{Context, Env};
+use super::continuation::{Continuation, Step};
 
-pub trait Combiner {}
+/// A combiner receives the operands of a combination along with the
+/// dynamic environment in which the combination is evaluated, and tells
+/// the evaluation loop how to continue.
+pub trait Combiner {
+    fn combine(&self, ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum NativeFnPtr {
+    /// A primitive computing its result from the operands only.
+    Pure(fn(Term) -> Result<Term, Error>),
+    /// A primitive taking control of the evaluation through the context.
+    Control(fn(&mut Context, Term, &Env) -> Result<Step, Error>),
+}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// A primitive operative implemented in Rust.
+#[derive(Debug, Clone)]
 pub struct NativeFn {
-    func: Box<fn(Term, Context) -> Result<Term, Error>>
+    name: &'static str,
+    func: NativeFnPtr
+}
+
+impl NativeFn {
+    pub fn new(name: &'static str, func: NativeFnPtr) -> Self {
+        Self { name, func }
+    }
+
+    pub fn name(&self) -> &'static str { self.name }
+}
+
+/// Primitives are identified by their names.
+impl PartialEq for NativeFn {
+    fn eq(&self, other: &Self) -> bool { self.name == other.name }
+}
+
+impl Combiner for NativeFn {
+    fn combine(&self, ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+        match self.func {
+            NativeFnPtr::Pure(func) => func(operands).map(Step::Return),
+            NativeFnPtr::Control(func) => func(ctx, operands, env),
+        }
+    }
+}
+
+/// A compound operative constructed by `$vau`.
+#[derive(Debug)]
+pub struct Operative {
+    formals: Term,
+    eformal: Term,
+    body: Term,
+    static_env: Env
+}
+
+impl Operative {
+    pub fn new(formals: Term, eformal: Term, body: Term, static_env: Env) -> Self {
+        Self { formals, eformal, body, static_env }
+    }
+}
+
+/// Compound operatives are compared by identity.
+impl PartialEq for Operative {
+    fn eq(&self, other: &Self) -> bool { std::ptr::eq(self, other) }
+}
+
+impl Combiner for Operative {
+    fn combine(&self, ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+        let local = self.static_env.child();
+        local.bind(&self.formals, operands)?;
+        local.bind(&self.eformal, Term::from(env.clone()))?;
+        ctx.eval_sequence(self.body.clone(), &local)
+    }
+}
+
+/// An applicative evaluates its operands before passing them to the
+/// underlying combiner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Applicative {
+    combiner: Rc<Term>
 }
 
-impl Combiner for NativeFn {}
+impl Applicative {
+    pub fn new(combiner: Term) -> Self {
+        Self { combiner: Rc::new(combiner) }
+    }
+
+    pub fn unwrap(&self) -> Term { (*self.combiner).clone() }
+}
+
+impl Combiner for Applicative {
+    fn combine(&self, ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+        ctx.eval_args(self.unwrap(), operands, env)
+    }
+}
+
+/// The underlying operative of `continuation->applicative`, which
+/// abnormally passes its operand tree to the continuation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Escape(Continuation);
+
+impl Escape {
+    pub fn new(cont: Continuation) -> Self { Self(cont) }
+
+    pub fn continuation(&self) -> &Continuation { &self.0 }
+}
+
+impl Combiner for Escape {
+    fn combine(&self, ctx: &mut Context, operands: Term, _: &Env) -> Result<Step, Error> {
+        ctx.pass(self.0.clone(), operands)
+    }
+}
+
+impl Term {
+    /// View the term as a combiner if it is one.
+    pub fn as_combiner(&self) -> Option<&dyn Combiner> {
+        if !self.is_combiner() { return None }
+        match &self.value {
+            TermValue::Applicative(app) => Some(app),
+            TermValue::Escape(escape) => Some(escape),
+            TermValue::Operative(op) => Some(op.as_ref()),
+            TermValue::PrimitiveFn(native) => Some(native),
+            _ => None
+        }
+    }
+}
+
+impl From<Operative> for Term {
+    fn from(value: Operative) -> Self { Term::from(Rc::new(value)) }
+}
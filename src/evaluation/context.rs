@@ -1,81 +1,381 @@
-
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
+use crate::seq;
 use crate::error::{Error, ErrorKind};
-use crate::parser::SrcInfo;
-use crate::syntax::Symbol;
+use crate::parser::{Span, SrcInfo};
+use super::{ground, library};
+use super::continuation::{Continuation, Frame, ResumeFn, Step};
+use super::combiner::{Applicative, Escape};
 use super::term::{Term, *};
 
+/// The default limit of pending frames, exceeding which is reported as an
+/// error instead of exhausting the memory.
+pub const DEFAULT_MAX_DEPTH: usize = 1_000_000;
+
 #[derive(Debug)]
 pub struct Context {
     pub(crate) env: Env,
-    src: Rc<RefCell<SrcInfo>>
+    src: Rc<RefCell<SrcInfo>>,
+    /// The continuation of the term being evaluated.
+    cont: Continuation,
+    /// Location of the innermost expression being evaluated.
+    span: Option<Rc<Span>>,
+    max_depth: usize,
+    /// The number of steps left before the evaluation is aborted, which is
+    /// unlimited if absent.
+    steps: Option<usize>
 }
 
 impl Context {
     pub fn new(src: Rc<RefCell<SrcInfo>>) -> Self {
-        Self { env: Env::new(), src }
+        let ground = Env::new();
+        ground::bind_ground(&ground);
+        library::bind_library(&ground);
+        // Programs are evaluated in a child of the ground environment,
+        // so that the ground bindings can't be changed by the user.
+        Self { env: ground.child(), src, cont: Continuation::root(), span: None, max_depth: DEFAULT_MAX_DEPTH, steps: None }
+    }
+
+    pub fn src(&self) -> &Rc<RefCell<SrcInfo>> { &self.src }
+
+    pub fn max_depth(&self) -> usize { self.max_depth }
+
+    pub fn set_max_depth(&mut self, depth: usize) { self.max_depth = depth }
+
+    /// Limit the number of terms evaluated from now on, which bounds the
+    /// evaluation of untrusted programs.
+    pub fn set_max_steps(&mut self, steps: Option<usize>) { self.steps = steps }
+
+    /// Evaluate the term in the root environment.
+    pub fn eval(&mut self, term: Term) -> Result<Term, Error> {
+        let env = self.env.clone();
+        self.eval_in(term, &env)
+    }
+
+    /// Evaluate the term with a new root continuation, the current one is
+    /// restored afterwards.
+    pub fn eval_in(&mut self, term: Term, env: &Env) -> Result<Term, Error> {
+        let saved = core::mem::replace(&mut self.cont, Continuation::root());
+        let span = self.span.clone();
+        let result = self.run(Step::Eval(term, env.clone()));
+        (self.cont, self.span) = (saved, span);
+        result
     }
 
-    // TODO: Add complete reduction implementation
-    pub fn eval(&mut self, mut term: Term) -> Result<(), Error> {
-        if !term.is_branch() {
-            self.reduce_leaf(&mut term)
-        } else {
-            self.reduce_branch(&mut term)
+    /// Run the evaluation loop until a value is passed to the root frame.
+    /// Errors without a location are located at the innermost expression
+    /// being evaluated.
+    fn run(&mut self, mut step: Step) -> Result<Term, Error> {
+        loop {
+            let result = match step {
+                Step::Eval(term, env) => {
+                    if let Some(span) = term.span() { self.span = Some(span.clone()) }
+                    self.tick().and_then(|_| self.reduce(term, &env))
+                },
+                Step::Return(value) => {
+                    let frame = self.cont.frame().clone();
+                    self.span = self.cont.span().cloned();
+                    if let Some(parent) = self.cont.parent() {
+                        self.cont = parent.clone();
+                    }
+                    match frame {
+                        Frame::Root => return Ok(value),
+                        frame => self.resume(frame, value)
+                    }
+                }
+            };
+            step = result.map_err(|err| match &self.span {
+                Some(span) if err.location().is_none() => err.with_location(span.as_ref().clone()),
+                _ => err
+            })?;
         }
     }
 
-    pub fn reduce_leaf(&mut self, term: &mut Term) -> Result<(), Error> {
-        let name: String;
-        match (term as &mut dyn TermAccess<Symbol>).try_access() {
-            Ok(symbol) => name = symbol.to_string(),
-            Err(_) => return Ok(()),
+    /// Consume a step of the evaluation, if the steps are limited.
+    fn tick(&mut self) -> Result<(), Error> {
+        match &mut self.steps {
+            Some(0) => Err(Error::new(ErrorKind::RecursionLimit)
+                .with_message("The evaluation exceeded the maximum number of steps.".to_string())),
+            Some(steps) => seq!(*steps -= 1, Ok(())),
+            None => Ok(())
         }
-        match self.env.lookup(&name) {
-            Some(ref mut term_ref) => {
-                // TODO
-                term.value_ref = term_ref.value_ref.clone();
-                Ok(())
+    }
+
+    /// Push a frame onto the current continuation, which is located at the
+    /// expression being evaluated.
+    pub(crate) fn push(&mut self, frame: Frame) -> Result<(), Error> {
+        if self.cont.depth() >= self.max_depth {
+            return Err(Error::new(ErrorKind::RecursionLimit)
+                .with_message(format!("The evaluation exceeded the maximum depth of {}.", self.max_depth)))
+        }
+        seq!(self.cont = self.cont.push_at(frame, self.span.clone()), Ok(()))
+    }
+
+    /// Suspend the rest of the computation in a frame resumed by `func`.
+    pub fn push_resume(&mut self, func: ResumeFn, data: Term, env: &Env) -> Result<(), Error> {
+        self.push(Frame::Resume { func, data, env: env.clone() })
+    }
+
+    fn resume(&mut self, frame: Frame, value: Term) -> Result<Step, Error> {
+        match frame {
+            Frame::Root => Ok(Step::Return(value)),
+            Frame::Combine { operands, env } => self.combine(value, operands, &env),
+            Frame::EvalArgs { combiner, remaining, evaluated, env } => {
+                let evaluated = Term::cons(value, evaluated);
+                match remaining.as_pair() {
+                    Some(pair) => {
+                        self.push(Frame::EvalArgs {
+                            combiner, remaining: pair.cdr(), evaluated, env: env.clone()
+                        })?;
+                        Ok(Step::Eval(pair.car(), env))
+                    },
+                    None => {
+                        let mut args = evaluated.to_list()?;
+                        args.reverse();
+                        self.combine(combiner, Term::list(args), &env)
+                    }
+                }
+            },
+            Frame::Sequence { rest, env } => self.eval_sequence(rest, &env),
+            Frame::Resume { func, data, env } => func(self, value, data, &env),
+            Frame::Extend { combiner, env } => self.combine(combiner, value, &env),
+            Frame::EntryGuard { .. } | Frame::ExitGuard { .. } => Ok(Step::Return(value)),
+            Frame::Intercept { interceptor, divert } => {
+                let divert = Applicative::new(Term::from(Escape::new(divert)));
+                self.combine(interceptor, Term::list([value, Term::from(divert)]), &Env::new())
             },
-            None => Err(Error::new(ErrorKind::FreeIdentifier)
-                .with_message(format!("Failed to resolve '{name}'."))
-                .return_error(&self.src.borrow(), (0, 0, 0).into(), 
-                    "".to_string()))
         }
     }
 
-    pub fn reduce_branch(&mut self, term: &mut Term) -> Result<(), Error> {
-        if term.is_branch() {
-            let front = term.sub_terms.front_mut().unwrap();
-            // TODO: Complete reduction.
-            match self.reduce_leaf(front) {
-                Err(err) => return Err(err),
-                _ => {}
+    /// The continuation receiving the result of the current combination.
+    pub fn current_continuation(&self) -> Continuation { self.cont.clone() }
+
+    /// Abnormally pass the value to the target continuation. The exit
+    /// guards between the current continuation and the common ancestor are
+    /// selected from inside out, then the entry guards between the common
+    /// ancestor and the target from outside in, and the value is filtered
+    /// through the interceptors of the selected clauses in that order.
+    pub fn pass(&mut self, target: Continuation, value: Term) -> Result<Step, Error> {
+        let ancestor = self.cont.common_ancestor(&target);
+        let mut interceptors = vec![];
+        let mut cont = Some(self.cont.clone());
+        while let Some(current) = cont.filter(|cont| Some(cont) != ancestor.as_ref()) {
+            if let Frame::ExitGuard { clauses } = current.frame() {
+                // The interceptor may divert to the outer guard frame.
+                let outer = current.parent().expect("An exit guard is always a child of an entry guard.");
+                interceptors.extend(select_interceptor(clauses, &target)?.map(|i| (i, outer.clone())));
+            }
+            cont = current.parent().cloned();
+        }
+        let mut entries = vec![];
+        let mut cont = Some(target.clone());
+        while let Some(current) = cont.filter(|cont| Some(cont) != ancestor.as_ref()) {
+            if let Frame::EntryGuard { clauses } = current.frame() {
+                entries.extend(select_interceptor(clauses, &self.cont)?.map(|i| (i, current.clone())));
             }
+            cont = current.parent().cloned();
+        }
+        interceptors.extend(entries.into_iter().rev());
+
+        self.cont = target;
+        // The first interceptor is pushed last to receive the value first.
+        for (interceptor, divert) in interceptors.into_iter().rev() {
+            self.push(Frame::Intercept { interceptor, divert })?;
+        }
+        Ok(Step::Return(value))
+    }
+
+    /// Evaluate a list of terms from left to right, and return the result of
+    /// the last one, or `#inert` for an empty list. The last term is
+    /// evaluated as a tail call.
+    pub fn eval_sequence(&mut self, terms: Term, env: &Env) -> Result<Step, Error> {
+        let Some(pair) = terms.as_pair() else {
+            // Reject an improper sequence.
+            terms.to_list()?;
+            return Ok(Step::Return(Term::inert()))
+        };
+        let rest = pair.cdr();
+        if !rest.is_nil() {
+            self.push(Frame::Sequence { rest, env: env.clone() })?;
+        }
+        Ok(Step::Eval(pair.car(), env.clone()))
+    }
+
+    /// Symbols are looked up in the environment, pairs are evaluated as
+    /// combinations, and other terms evaluate to themselves.
+    fn reduce(&mut self, term: Term, env: &Env) -> Result<Step, Error> {
+        if let Some(pair) = term.as_pair() {
+            self.push(Frame::Combine { operands: pair.cdr(), env: env.clone() })?;
+            return Ok(Step::Eval(pair.car(), env.clone()))
+        }
+        let TermValue::Sym(symbol) = &term.value else {
+            return Ok(Step::Return(term))
+        };
+        env.lookup(symbol.as_ref()).map(Step::Return).ok_or_else(|| Error::new(ErrorKind::FreeIdentifier)
+            .with_message(format!("Failed to resolve '{symbol}'.")))
+    }
+
+    /// Pass the operands to the combiner, the operands are evaluated only
+    /// if the combiner is applicative.
+    pub fn combine(&mut self, combiner: Term, operands: Term, env: &Env) -> Result<Step, Error> {
+        match combiner.as_combiner() {
+            Some(combiner) => combiner.combine(self, operands, env),
+            None => Err(Error::new(ErrorKind::TypeMismatch)
+                .with_message(format!("Expected a combiner, but found {}.", combiner.type_name())))
+        }
+    }
+
+    /// Evaluate the operands from left to right before combining the
+    /// combiner with the list of the arguments.
+    pub(crate) fn eval_args(&mut self, combiner: Term, operands: Term, env: &Env) -> Result<Step, Error> {
+        operands.to_list()?;
+        match operands.as_pair() {
+            Some(pair) => {
+                self.push(Frame::EvalArgs {
+                    combiner, remaining: pair.cdr(), evaluated: Term::new(), env: env.clone()
+                })?;
+                Ok(Step::Eval(pair.car(), env.clone()))
+            },
+            None => self.combine(combiner, operands, env)
         }
-        Ok(())
     }
 }
 
-#[derive(Debug)]
-pub struct Env {
-    bindings: HashMap<String, Term>
+/// Find the underlying combiner of the interceptor in the first clause
+/// whose selector contains the continuation.
+fn select_interceptor(clauses: &Term, cont: &Continuation) -> Result<Option<Term>, Error> {
+    for clause in clauses.to_list()? {
+        let (selector, interceptor) = guard_clause(&clause)?;
+        if cont.is_within(&selector) { return Ok(Some(interceptor)) }
+    }
+    Ok(None)
+}
+
+/// Destruct a guard clause `(selector interceptor)` into the selector
+/// continuation and the underlying combiner of the interceptor.
+pub(crate) fn guard_clause(clause: &Term) -> Result<(Continuation, Term), Error> {
+    let [selector, interceptor] = <[Term; 2]>::try_from(clause.to_list()?)
+        .map_err(|_| Error::new(ErrorKind::TypeMismatch)
+            .with_message(format!("Expected a guard clause (selector interceptor), but found {clause}.")))?;
+    let selector = (&selector as &dyn TermAccess<Continuation>).try_access()?.clone();
+    let interceptor = (&interceptor as &dyn TermAccess<Applicative>).try_access()?.unwrap();
+    Ok((selector, interceptor))
+}
+
+/// A shared handle to an environment, which consists of its local bindings
+/// and a list of parent environments. Environments can be captured by
+/// combiners and passed around as first-class values.
+#[derive(Clone)]
+pub struct Env(Rc<EnvFrame>);
+
+struct EnvFrame {
+    bindings: RefCell<HashMap<String, Term>>,
+    parents: Vec<Env>
 }
 
-// TODO: Implement linked environments.
 impl Env {
     pub fn new() -> Self {
-        Self { bindings: HashMap::new() }
+        Self::with_parents(vec![])
+    }
+
+    pub fn with_parents(parents: Vec<Env>) -> Self {
+        Self(Rc::new(EnvFrame { bindings: RefCell::new(HashMap::new()), parents }))
+    }
+
+    /// Create an empty environment whose only parent is this one.
+    pub fn child(&self) -> Self {
+        Self::with_parents(vec![self.clone()])
+    }
+
+    pub fn parents(&self) -> &[Env] { &self.0.parents }
+
+    /// Look up the name in the local bindings, and then in the parents
+    /// with a depth-first search from left to right.
+    pub fn lookup(&self, name: &str) -> Option<Term> {
+        // Only the environments with multiple parents need to be
+        // remembered, the common chain of single parents is followed
+        // without allocation.
+        let mut pending = vec![];
+        let mut env = self;
+        loop {
+            if let Some(term) = env.0.bindings.borrow().get(name) {
+                return Some(term.clone())
+            }
+            env = match env.0.parents.as_slice() {
+                [] => pending.pop()?,
+                [parent] => parent,
+                [first, rest @ ..] => seq!(pending.extend(rest.iter().rev()), first),
+            }
+        }
     }
 
-    pub fn lookup(&mut self, name: &String) -> Option<&mut Term> {
-        self.bindings.get_mut(name)
+    pub fn binds(&self, name: &str) -> bool {
+        self.lookup(name).is_some()
+    }
+
+    /// Bind the name in the local bindings, shadowing any binding of the
+    /// parents. The parents themselves are never mutated.
+    pub fn insert(&self, name: &str, term: Term) -> Option<Term> {
+        self.0.bindings.borrow_mut().insert(name.to_string(), term)
+    }
+
+    /// Match the parameter tree against the object and bind the symbols.
+    pub fn bind(&self, formals: &Term, object: Term) -> Result<(), Error> {
+        match (&formals.value, &object.value) {
+            (TermValue::Unit(UnitValue::Ignore), _) | (TermValue::Nil, TermValue::Nil) => return Ok(()),
+            (TermValue::Sym(symbol), _) => return seq!(self.insert(symbol.as_ref(), object), Ok(())),
+            (TermValue::Pair(formal), TermValue::Pair(sub_object)) => {
+                self.bind(&formal.car(), sub_object.car())?;
+                return self.bind(&formal.cdr(), sub_object.cdr())
+            },
+            _ => {}
+        }
+        Err(Error::new(ErrorKind::TypeMismatch)
+            .with_message(format!("Failed to match the operands '{object}' with the parameters '{formals}'.")))
+    }
+}
+
+impl Default for Env {
+    fn default() -> Self { Env::new() }
+}
+
+/// Environments are compared by identity.
+impl PartialEq for Env {
+    fn eq(&self, other: &Self) -> bool { Rc::ptr_eq(&self.0, &other.0) }
+}
+
+impl std::fmt::Debug for Env {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Env({} bindings, {} parents)", self.0.bindings.borrow().len(), self.0.parents.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Env, Term};
+
+    #[test]
+    fn env_lookup_parents() {
+        let (a, b) = (Env::new(), Env::new());
+        a.insert("x", Term::from(1));
+        b.insert("x", Term::from(2));
+        b.insert("y", Term::from(3));
+        let env = Env::with_parents(vec![a.clone(), b.clone()]);
+        assert_eq!(env.lookup("x"), Some(Term::from(1)));
+        assert_eq!(env.lookup("y"), Some(Term::from(3)));
+        assert_eq!(env.lookup("z"), None);
     }
 
-    pub fn insert(&mut self, name: &String, term: Term) -> Option<Term> {
-        self.bindings.insert(name.to_string(), term)
+    #[test]
+    fn env_insert_shadowing() {
+        let parent = Env::new();
+        parent.insert("x", Term::from(1));
+        let child = parent.child();
+        child.insert("x", Term::from(2));
+        assert_eq!(child.lookup("x"), Some(Term::from(2)));
+        assert_eq!(parent.lookup("x"), Some(Term::from(1)));
+        assert!(child.binds("x") && !child.binds("y"));
     }
 }
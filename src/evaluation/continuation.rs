@@ -0,0 +1,133 @@
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::parser::Span;
+use super::context::{Context, Env};
+use super::term::Term;
+
+/// What the evaluation loop should do next, returned by combiners instead
+/// of recursing into the evaluator, so that tail calls take no Rust stack.
+#[derive(Debug)]
+pub enum Step {
+    /// Pass the value to the current continuation.
+    Return(Term),
+    /// Evaluate the term in the environment with the current continuation.
+    Eval(Term, Env),
+}
+
+/// Resume a suspended computation with the value received by the frame,
+/// the data and the environment stored in the frame.
+pub type ResumeFn = fn(&mut Context, Term, Term, &Env) -> Result<Step, Error>;
+
+/// A pending computation waiting for a value.
+#[derive(Debug, Clone)]
+pub(crate) enum Frame {
+    /// The bottom of every continuation, the value received is the result
+    /// of the whole evaluation.
+    Root,
+    /// The value is a combiner to be combined with the operands.
+    Combine { operands: Term, env: Env },
+    /// The value is an evaluated argument, `evaluated` holds the previous
+    /// ones in reverse order.
+    EvalArgs { combiner: Term, remaining: Term, evaluated: Term, env: Env },
+    /// The value is discarded and the rest terms are evaluated in order.
+    Sequence { rest: Term, env: Env },
+    /// The value is passed to a native function together with its data.
+    Resume { func: ResumeFn, data: Term, env: Env },
+    /// The value is the operand tree of the combiner, made by
+    /// `extend-continuation`.
+    Extend { combiner: Term, env: Env },
+    /// The outer frame of a guarded continuation, whose clauses intercept
+    /// the abnormal passes entering it.
+    EntryGuard { clauses: Term },
+    /// The inner frame of a guarded continuation, whose clauses intercept
+    /// the abnormal passes exiting it.
+    ExitGuard { clauses: Term },
+    /// The value is passed to the underlying combiner of an interceptor
+    /// along with an applicative escaping to `divert`.
+    Intercept { interceptor: Term, divert: Continuation },
+}
+
+/// A continuation is an immutable chain of frames, which can be shared
+/// by multiple computations.
+#[derive(Clone)]
+pub struct Continuation(Rc<ContinuationNode>);
+
+#[derive(Debug)]
+struct ContinuationNode {
+    frame: Frame,
+    parent: Option<Continuation>,
+    depth: usize,
+    /// Location of the expression being evaluated when the frame is pushed.
+    span: Option<Rc<Span>>
+}
+
+impl Continuation {
+    pub(crate) fn root() -> Self {
+        Self(Rc::new(ContinuationNode { frame: Frame::Root, parent: None, depth: 0, span: None }))
+    }
+
+    /// Push a frame located at the same expression as this one.
+    pub(crate) fn push(&self, frame: Frame) -> Self {
+        self.push_at(frame, self.0.span.clone())
+    }
+
+    pub(crate) fn push_at(&self, frame: Frame, span: Option<Rc<Span>>) -> Self {
+        Self(Rc::new(ContinuationNode { frame, parent: Some(self.clone()), depth: self.0.depth + 1, span }))
+    }
+
+    pub(crate) fn frame(&self) -> &Frame { &self.0.frame }
+
+    pub(crate) fn parent(&self) -> Option<&Continuation> { self.0.parent.as_ref() }
+
+    pub(crate) fn span(&self) -> Option<&Rc<Span>> { self.0.span.as_ref() }
+
+    /// Number of frames above the root.
+    pub fn depth(&self) -> usize { self.0.depth }
+
+    /// Whether the continuation is the other one or one of its descendants.
+    pub fn is_within(&self, other: &Continuation) -> bool {
+        let mut cont = self;
+        while cont.depth() > other.depth() {
+            cont = cont.parent().expect("Only the root has no parent.");
+        }
+        cont == other
+    }
+
+    /// The nearest continuation of which both are within, which is `None`
+    /// if they don't share the same root.
+    pub fn common_ancestor(&self, other: &Continuation) -> Option<Continuation> {
+        let (mut a, mut b) = (self, other);
+        while a.depth() > b.depth() { a = a.parent()? }
+        while b.depth() > a.depth() { b = b.parent()? }
+        while a != b {
+            (a, b) = (a.parent()?, b.parent()?);
+        }
+        Some(a.clone())
+    }
+}
+
+impl std::fmt::Debug for Continuation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Continuation({} frames)", self.depth())
+    }
+}
+
+/// Continuations are compared by identity.
+impl PartialEq for Continuation {
+    fn eq(&self, other: &Self) -> bool { Rc::ptr_eq(&self.0, &other.0) }
+}
+
+/// Drop the chain of parents iteratively, since a deep continuation would
+/// overflow the stack with the recursive drop.
+impl Drop for ContinuationNode {
+    fn drop(&mut self) {
+        let mut parent = self.parent.take();
+        while let Some(Continuation(node)) = parent {
+            parent = match Rc::try_unwrap(node) {
+                Ok(mut node) => node.parent.take(),
+                Err(_) => None
+            }
+        }
+    }
+}
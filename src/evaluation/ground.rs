@@ -0,0 +1,283 @@
+//! Core combiners of the ground environment.
+
+use std::rc::Rc;
+
+use crate::error::{Error, ErrorKind};
+use crate::syntax::Symbol;
+use super::combiner::{Applicative, Escape, NativeFn, NativeFnPtr::{self, *}, Operative};
+use super::context::{guard_clause, Context, Env};
+use super::continuation::{Continuation, Frame, Step};
+use super::term::*;
+
+pub fn bind_ground(env: &Env) {
+    bind_operative(env, "$vau", Control(vau));
+    bind_operative(env, "$lambda", Control(lambda));
+    bind_operative(env, "$define!", Control(define));
+    bind_operative(env, "$set!", Control(set));
+    bind_operative(env, "$binds?", Control(binds));
+    bind_operative(env, "$let", Control(let_));
+    bind_operative(env, "$if", Control(if_));
+    bind_operative(env, "$cond", Control(cond));
+    bind_operative(env, "$sequence", Control(sequence));
+    bind_operative(env, "$quote", Pure(quote));
+    bind_applicative(env, "wrap", Pure(wrap));
+    bind_applicative(env, "unwrap", Pure(unwrap));
+    bind_applicative(env, "eval", Control(eval));
+    bind_applicative(env, "apply", Control(apply));
+    bind_applicative(env, "make-environment", Pure(make_environment));
+    bind_applicative(env, "get-current-environment", Control(get_current_environment));
+    bind_applicative(env, "call/cc", Control(call_cc));
+    bind_applicative(env, "extend-continuation", Pure(extend_continuation));
+    bind_applicative(env, "guard-continuation", Pure(guard_continuation));
+    bind_applicative(env, "continuation->applicative", Pure(continuation_to_applicative));
+}
+
+pub(crate) fn bind_operative(env: &Env, name: &'static str, func: NativeFnPtr) {
+    env.insert(name, Term::from(NativeFn::new(name, func)));
+}
+
+pub(crate) fn bind_applicative(env: &Env, name: &'static str, func: NativeFnPtr) {
+    env.insert(name, Term::from(Applicative::new(Term::from(NativeFn::new(name, func)))));
+}
+
+/// Destruct the operands into exactly `N` terms.
+pub(crate) fn expect_args<const N: usize>(operands: Term, name: &str) -> Result<[Term; N], Error> {
+    let operands = operands.to_list()?;
+    let len = operands.len();
+    <[Term; N]>::try_from(operands)
+        .map_err(|_| Error::new(ErrorKind::TypeMismatch)
+            .with_message(format!("'{name}' expects {N} operand(s), but {len} were given.")))
+}
+
+/// Destruct the operands into at least `N` terms and the rest of the list.
+pub(crate) fn expect_at_least<const N: usize>(operands: Term, name: &str) -> Result<([Term; N], Term), Error> {
+    let mut terms = Vec::with_capacity(N);
+    let mut rest = operands;
+    while terms.len() < N {
+        let Some(pair) = rest.as_pair().cloned() else {
+            return Err(Error::new(ErrorKind::TypeMismatch)
+                .with_message(format!("'{name}' expects at least {N} operand(s), but {} were given.", terms.len())))
+        };
+        terms.push(pair.car());
+        rest = pair.cdr();
+    }
+    Ok((expect_args(Term::list(terms), name)?, rest))
+}
+
+pub(crate) fn expect_env(term: &Term) -> Result<Env, Error> {
+    (term as &dyn TermAccess<Env>).try_access().cloned()
+}
+
+/// `($quote datum)` results in the datum unevaluated.
+pub(crate) fn quote(operands: Term) -> Result<Term, Error> {
+    let [datum] = expect_args(operands, "$quote")?;
+    Ok(datum)
+}
+
+fn vau(_: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    let ([formals, eformal], body) = expect_at_least(operands, "$vau")?;
+    Ok(Step::Return(Term::from(Operative::new(formals, eformal, body, env.clone()))))
+}
+
+fn lambda(_: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    let ([formals], body) = expect_at_least(operands, "$lambda")?;
+    let operative = Operative::new(formals, Term::ignore(), body, env.clone());
+    Ok(Step::Return(Term::from(Applicative::new(Term::from(operative)))))
+}
+
+fn define(ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    let [formals, expr] = expect_args(operands, "$define!")?;
+    ctx.push_resume(resume_define, formals, env)?;
+    Ok(Step::Eval(expr, env.clone()))
+}
+
+fn resume_define(_: &mut Context, value: Term, formals: Term, env: &Env) -> Result<Step, Error> {
+    env.bind(&formals, value)?;
+    Ok(Step::Return(Term::inert()))
+}
+
+fn set(ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    let [target, formals, expr] = expect_args(operands, "$set!")?;
+    ctx.push_resume(resume_set_target, Term::list([formals, expr]), env)?;
+    Ok(Step::Eval(target, env.clone()))
+}
+
+fn resume_set_target(ctx: &mut Context, target: Term, data: Term, env: &Env) -> Result<Step, Error> {
+    let target = expect_env(&target)?;
+    let [formals, expr] = expect_args(data, "$set!")?;
+    ctx.push_resume(resume_set_value, formals, &target)?;
+    Ok(Step::Eval(expr, env.clone()))
+}
+
+fn resume_set_value(_: &mut Context, value: Term, formals: Term, target: &Env) -> Result<Step, Error> {
+    target.bind(&formals, value)?;
+    Ok(Step::Return(Term::inert()))
+}
+
+fn binds(ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    let ([target], symbols) = expect_at_least(operands, "$binds?")?;
+    ctx.push_resume(resume_binds, symbols, env)?;
+    Ok(Step::Eval(target, env.clone()))
+}
+
+fn resume_binds(_: &mut Context, target: Term, symbols: Term, _: &Env) -> Result<Step, Error> {
+    let target = expect_env(&target)?;
+    for symbol in symbols.to_list()? {
+        let symbol = (&symbol as &dyn TermAccess<Symbol>).try_access()?;
+        if !target.binds(symbol.as_ref()) { return Ok(Step::Return(Term::from(false))) }
+    }
+    Ok(Step::Return(Term::from(true)))
+}
+
+/// `($let ((formals expr) ...) . body)` evaluates the expressions in the
+/// dynamic environment, and the body in a child of it.
+fn let_(ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    let ([bindings], body) = expect_at_least(operands, "$let")?;
+    bindings.to_list()?;
+    next_let_binding(ctx, bindings, body, env.child(), env)
+}
+
+fn next_let_binding(ctx: &mut Context, bindings: Term, body: Term, local: Env, env: &Env) -> Result<Step, Error> {
+    let Some(binding) = bindings.as_pair() else {
+        return ctx.eval_sequence(body, &local)
+    };
+    let [formals, expr] = expect_args(binding.car(), "$let")?;
+    ctx.push_resume(resume_let, Term::list([formals, binding.cdr(), body, Term::from(local)]), env)?;
+    Ok(Step::Eval(expr, env.clone()))
+}
+
+fn resume_let(ctx: &mut Context, value: Term, data: Term, env: &Env) -> Result<Step, Error> {
+    let [formals, bindings, body, local] = expect_args(data, "$let")?;
+    let local = expect_env(&local)?;
+    local.bind(&formals, value)?;
+    next_let_binding(ctx, bindings, body, local, env)
+}
+
+fn if_(ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    let [test, consequent, alternative] = expect_args(operands, "$if")?;
+    ctx.push_resume(resume_if, Term::list([consequent, alternative]), env)?;
+    Ok(Step::Eval(test, env.clone()))
+}
+
+fn resume_if(_: &mut Context, test: Term, branches: Term, env: &Env) -> Result<Step, Error> {
+    let [consequent, alternative] = expect_args(branches, "$if")?;
+    match (&test as &dyn TermAccess<bool>).try_access()? {
+        true => Ok(Step::Eval(consequent, env.clone())),
+        false => Ok(Step::Eval(alternative, env.clone())),
+    }
+}
+
+/// `($cond (test . body) ...)` evaluates the body of the first clause whose
+/// test evaluates to `#t`, or results in `#inert` if there is no such one.
+fn cond(ctx: &mut Context, clauses: Term, env: &Env) -> Result<Step, Error> {
+    clauses.to_list()?;
+    let Some(clause) = clauses.as_pair() else {
+        return Ok(Step::Return(Term::inert()))
+    };
+    let ([test], body) = expect_at_least(clause.car(), "$cond")?;
+    ctx.push_resume(resume_cond, Term::cons(body, clause.cdr()), env)?;
+    Ok(Step::Eval(test, env.clone()))
+}
+
+fn resume_cond(ctx: &mut Context, test: Term, data: Term, env: &Env) -> Result<Step, Error> {
+    let pair = (&data as &dyn TermAccess<Rc<Pair>>).try_access()?;
+    match (&test as &dyn TermAccess<bool>).try_access()? {
+        true => ctx.eval_sequence(pair.car(), env),
+        false => cond(ctx, pair.cdr(), env),
+    }
+}
+
+fn sequence(ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    ctx.eval_sequence(operands, env)
+}
+
+fn wrap(operands: Term) -> Result<Term, Error> {
+    let [combiner] = expect_args(operands, "wrap")?;
+    if !combiner.is_combiner() {
+        return Err(Error::new(ErrorKind::TypeMismatch)
+            .with_message(format!("Expected a combiner, but found {}.", combiner.type_name())))
+    }
+    Ok(Term::from(Applicative::new(combiner)))
+}
+
+fn unwrap(operands: Term) -> Result<Term, Error> {
+    let [applicative] = expect_args(operands, "unwrap")?;
+    Ok((&applicative as &dyn TermAccess<Applicative>).try_access()?.unwrap())
+}
+
+fn make_environment(operands: Term) -> Result<Term, Error> {
+    let parents = operands.to_list()?.iter().map(expect_env).collect::<Result<_, _>>()?;
+    Ok(Term::from(Env::with_parents(parents)))
+}
+
+fn get_current_environment(_: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    let [] = expect_args(operands, "get-current-environment")?;
+    Ok(Step::Return(Term::from(env.clone())))
+}
+
+fn eval(_: &mut Context, operands: Term, _: &Env) -> Result<Step, Error> {
+    let [expr, env] = expect_args(operands, "eval")?;
+    Ok(Step::Eval(expr, expect_env(&env)?))
+}
+
+/// `(apply applicative object [env])` combines the underlying combiner of
+/// the applicative with the object in the environment, which defaults to
+/// an empty one.
+fn apply(ctx: &mut Context, operands: Term, _: &Env) -> Result<Step, Error> {
+    let ([applicative, object], env) = expect_at_least(operands, "apply")?;
+    let env = match env.to_list()?.as_slice() {
+        [] => Env::new(),
+        [env] => expect_env(env)?,
+        rest => return Err(Error::new(ErrorKind::TypeMismatch)
+            .with_message(format!("'apply' expects 2 or 3 operand(s), but {} were given.", rest.len() + 2)))
+    };
+    let combiner = (&applicative as &dyn TermAccess<Applicative>).try_access()?.unwrap();
+    ctx.combine(combiner, object, &env)
+}
+
+fn expect_continuation(term: &Term) -> Result<Continuation, Error> {
+    (term as &dyn TermAccess<Continuation>).try_access().cloned()
+}
+
+/// `(call/cc combiner)` combines the combiner with a list of the
+/// continuation of the call in the dynamic environment, as a tail call.
+fn call_cc(ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    let [combiner] = expect_args(operands, "call/cc")?;
+    let cont = Term::from(ctx.current_continuation());
+    ctx.combine(combiner, Term::list([cont]), env)
+}
+
+/// `(extend-continuation continuation applicative [env])` makes a child of
+/// the continuation, which passes the value it receives as the operand
+/// tree to the underlying combiner of the applicative in the environment,
+/// which defaults to an empty one.
+fn extend_continuation(operands: Term) -> Result<Term, Error> {
+    let ([cont, applicative], env) = expect_at_least(operands, "extend-continuation")?;
+    let env = match env.to_list()?.as_slice() {
+        [] => Env::new(),
+        [env] => expect_env(env)?,
+        rest => return Err(Error::new(ErrorKind::TypeMismatch)
+            .with_message(format!("'extend-continuation' expects 2 or 3 operand(s), but {} were given.", rest.len() + 2)))
+    };
+    let combiner = (&applicative as &dyn TermAccess<Applicative>).try_access()?.unwrap();
+    Ok(Term::from(expect_continuation(&cont)?.push(Frame::Extend { combiner, env })))
+}
+
+/// `(guard-continuation entry-guards continuation exit-guards)` makes an
+/// outer child of the continuation with the entry guards, and returns an
+/// inner child of it with the exit guards. Each guard is a clause
+/// `(selector interceptor)`, where the interceptor is called with the
+/// value being passed and an applicative diverting to the outer one.
+fn guard_continuation(operands: Term) -> Result<Term, Error> {
+    let [entry, cont, exit] = expect_args(operands, "guard-continuation")?;
+    for clause in entry.to_list()?.iter().chain(exit.to_list()?.iter()) {
+        guard_clause(clause)?;
+    }
+    let outer = expect_continuation(&cont)?.push(Frame::EntryGuard { clauses: entry });
+    Ok(Term::from(outer.push(Frame::ExitGuard { clauses: exit })))
+}
+
+fn continuation_to_applicative(operands: Term) -> Result<Term, Error> {
+    let [cont] = expect_args(operands, "continuation->applicative")?;
+    Ok(Term::from(Applicative::new(Term::from(Escape::new(expect_continuation(&cont)?)))))
+}
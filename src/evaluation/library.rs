@@ -0,0 +1,500 @@
+//! The standard library bound in the ground environment along with the
+//! core combiners.
+
+use std::cmp::Ordering;
+use std::io::Write;
+use std::rc::Rc;
+
+use num_traits::ToPrimitive;
+
+use crate::if_or;
+use crate::error::{Error, ErrorKind};
+use crate::syntax::Symbol;
+use super::combiner::{Applicative, NativeFn, NativeFnPtr::*};
+use super::context::{Context, Env};
+use super::continuation::{Continuation, Step};
+use super::number::Number;
+use super::ground::{bind_applicative, bind_operative, expect_args, expect_at_least, quote};
+use super::term::*;
+
+pub fn bind_library(env: &Env) {
+    bind_applicative(env, "+", Pure(add));
+    bind_applicative(env, "-", Pure(sub));
+    bind_applicative(env, "*", Pure(mul));
+    bind_applicative(env, "/", Pure(div));
+    bind_applicative(env, "div", Pure(div_euclid));
+    bind_applicative(env, "mod", Pure(mod_euclid));
+    bind_applicative(env, "div-and-mod", Pure(div_and_mod));
+    bind_applicative(env, "min", Pure(min));
+    bind_applicative(env, "max", Pure(max));
+    bind_applicative(env, "abs", Pure(abs));
+    bind_applicative(env, "floor", Pure(floor));
+    bind_applicative(env, "ceiling", Pure(ceiling));
+    bind_applicative(env, "truncate", Pure(truncate));
+    bind_applicative(env, "round", Pure(round));
+    bind_applicative(env, "expt", Pure(expt));
+    bind_applicative(env, "sqrt", Pure(sqrt));
+    bind_applicative(env, "numerator", Pure(numerator));
+    bind_applicative(env, "denominator", Pure(denominator));
+    bind_applicative(env, "exact->inexact", Pure(exact_to_inexact));
+    bind_applicative(env, "inexact->exact", Pure(inexact_to_exact));
+
+    bind_applicative(env, "=?", Pure(num_eq));
+    bind_applicative(env, "<?", Pure(num_lt));
+    bind_applicative(env, "<=?", Pure(num_le));
+    bind_applicative(env, ">?", Pure(num_gt));
+    bind_applicative(env, ">=?", Pure(num_ge));
+
+    bind_applicative(env, "not?", Pure(not));
+    bind_applicative(env, "and?", Pure(and));
+    bind_applicative(env, "or?", Pure(or));
+    bind_operative(env, "$and?", Control(and_operative));
+    bind_operative(env, "$or?", Control(or_operative));
+
+    bind_applicative(env, "eq?", Pure(eq));
+    bind_applicative(env, "equal?", Pure(equal));
+
+    bind_applicative(env, "boolean?", Pure(is_boolean));
+    bind_applicative(env, "integer?", Pure(is_integer));
+    bind_applicative(env, "number?", Pure(is_number));
+    bind_applicative(env, "real?", Pure(is_number));
+    bind_applicative(env, "rational?", Pure(is_rational));
+    bind_applicative(env, "exact?", Pure(is_exact));
+    bind_applicative(env, "inexact?", Pure(is_inexact));
+    bind_applicative(env, "string?", Pure(is_string));
+    bind_applicative(env, "char?", Pure(is_char));
+    bind_applicative(env, "symbol?", Pure(is_symbol));
+    bind_applicative(env, "inert?", Pure(is_inert));
+    bind_applicative(env, "ignore?", Pure(is_ignore));
+    bind_applicative(env, "environment?", Pure(is_environment));
+    bind_applicative(env, "continuation?", Pure(is_continuation));
+    bind_applicative(env, "combiner?", Pure(is_combiner));
+    bind_applicative(env, "operative?", Pure(is_operative));
+    bind_applicative(env, "applicative?", Pure(is_applicative));
+    bind_applicative(env, "null?", Pure(is_null));
+    bind_applicative(env, "pair?", Pure(is_pair));
+    bind_applicative(env, "vector?", Pure(is_vector));
+
+    bind_applicative(env, "char->integer", Pure(char_to_integer));
+    bind_applicative(env, "integer->char", Pure(integer_to_char));
+
+    bind_applicative(env, "display", Pure(display));
+    bind_applicative(env, "write", Pure(write));
+    bind_applicative(env, "newline", Pure(newline));
+
+    bind_applicative(env, "list", Pure(list));
+    bind_applicative(env, "list*", Pure(list_star));
+    bind_applicative(env, "cons", Pure(cons));
+    bind_applicative(env, "car", Pure(car));
+    bind_applicative(env, "cdr", Pure(cdr));
+    bind_applicative(env, "set-car!", Pure(set_car));
+    bind_applicative(env, "set-cdr!", Pure(set_cdr));
+    bind_applicative(env, "length", Pure(length));
+    bind_applicative(env, "append", Pure(append));
+
+    bind_applicative(env, "vector", Pure(vector));
+    bind_applicative(env, "vector-length", Pure(vector_length));
+    bind_applicative(env, "vector-ref", Pure(vector_ref));
+    bind_applicative(env, "vector->list", Pure(vector_to_list));
+    bind_applicative(env, "list->vector", Pure(list_to_vector));
+    bind_operative(env, "$quasiquote", Control(quasiquote));
+}
+
+fn numbers(operands: Term) -> Result<Vec<Number>, Error> {
+    operands.to_list()?.iter()
+        .map(|term| (term as &dyn TermAccess<Number>).try_access().cloned())
+        .collect()
+}
+
+fn number(term: &Term) -> Result<&Number, Error> {
+    (term as &dyn TermAccess<Number>).try_access()
+}
+
+fn add(operands: Term) -> Result<Term, Error> {
+    Ok(Term::from(numbers(operands)?.iter().fold(Number::from(0), |acc, n| &acc + n)))
+}
+
+fn mul(operands: Term) -> Result<Term, Error> {
+    Ok(Term::from(numbers(operands)?.iter().fold(Number::from(1), |acc, n| &acc * n)))
+}
+
+/// With a single operand `-` negates it, otherwise the rest operands are
+/// subtracted from the first one.
+fn sub(operands: Term) -> Result<Term, Error> {
+    let ([first], rest) = expect_at_least(operands, "-")?;
+    let first = number(&first)?;
+    let rest = numbers(rest)?;
+    if rest.is_empty() { return Ok(Term::from(-first)) }
+    Ok(Term::from(rest.iter().fold(first.clone(), |acc, n| &acc - n)))
+}
+
+/// With a single operand `/` results in its reciprocal, otherwise the first
+/// operand is divided by the rest ones. Exact division results in exact
+/// rationals.
+fn div(operands: Term) -> Result<Term, Error> {
+    let ([first], rest) = expect_at_least(operands, "/")?;
+    let first = number(&first)?;
+    let rest = numbers(rest)?;
+    if rest.is_empty() { return Number::from(1).div(first).map(Term::from) }
+    rest.iter().try_fold(first.clone(), |acc, n| acc.div(n)).map(Term::from)
+}
+
+fn div_euclid(operands: Term) -> Result<Term, Error> {
+    let [n1, n2] = expect_args(operands, "div")?;
+    Ok(Term::from(number(&n1)?.div_mod(number(&n2)?, "div")?.0))
+}
+
+fn mod_euclid(operands: Term) -> Result<Term, Error> {
+    let [n1, n2] = expect_args(operands, "mod")?;
+    Ok(Term::from(number(&n1)?.div_mod(number(&n2)?, "mod")?.1))
+}
+
+fn div_and_mod(operands: Term) -> Result<Term, Error> {
+    let [n1, n2] = expect_args(operands, "div-and-mod")?;
+    let (div, rem) = number(&n1)?.div_mod(number(&n2)?, "div-and-mod")?;
+    Ok(Term::list([Term::from(div), Term::from(rem)]))
+}
+
+/// Check whether the relation holds for every adjacent pair of operands,
+/// where NaN is unordered with any number.
+fn compare(operands: Term, relation: fn(Ordering) -> bool) -> Result<Term, Error> {
+    let numbers = numbers(operands)?;
+    Ok(Term::from(numbers.windows(2).all(|pair| pair[0].compare(&pair[1]).is_some_and(relation))))
+}
+
+fn num_eq(operands: Term) -> Result<Term, Error> { compare(operands, Ordering::is_eq) }
+
+fn num_lt(operands: Term) -> Result<Term, Error> { compare(operands, Ordering::is_lt) }
+
+fn num_le(operands: Term) -> Result<Term, Error> { compare(operands, Ordering::is_le) }
+
+fn num_gt(operands: Term) -> Result<Term, Error> { compare(operands, Ordering::is_gt) }
+
+fn num_ge(operands: Term) -> Result<Term, Error> { compare(operands, Ordering::is_ge) }
+
+/// Select the extreme operand by the ordering, the result is inexact if
+/// any operand is inexact.
+fn extreme(operands: Term, name: &str, ordering: Ordering) -> Result<Term, Error> {
+    let ([first], rest) = expect_at_least(operands, name)?;
+    let numbers = numbers(rest)?;
+    let mut result = number(&first)?.clone();
+    for n in &numbers {
+        if n.compare(&result) == Some(ordering) { result = n.clone() }
+    }
+    let exact = result.is_exact() && numbers.iter().all(Number::is_exact);
+    Ok(Term::from(if_or!(exact, result.clone(), result.to_inexact())))
+}
+
+fn min(operands: Term) -> Result<Term, Error> { extreme(operands, "min", Ordering::Less) }
+
+fn max(operands: Term) -> Result<Term, Error> { extreme(operands, "max", Ordering::Greater) }
+
+macro_rules! unary_number {
+    ($name: ident, $id: literal, $n: ident => $result: expr) => {
+        fn $name(operands: Term) -> Result<Term, Error> {
+            let [n] = expect_args(operands, $id)?;
+            let $n = number(&n)?;
+            Ok(Term::from($result))
+        }
+    };
+}
+
+unary_number!(abs, "abs", n => n.abs());
+unary_number!(floor, "floor", n => n.floor());
+unary_number!(ceiling, "ceiling", n => n.ceiling());
+unary_number!(truncate, "truncate", n => n.truncate());
+unary_number!(round, "round", n => n.round());
+unary_number!(sqrt, "sqrt", n => n.sqrt()?);
+unary_number!(numerator, "numerator", n => n.numerator()?);
+unary_number!(denominator, "denominator", n => n.denominator()?);
+unary_number!(exact_to_inexact, "exact->inexact", n => n.to_inexact());
+unary_number!(inexact_to_exact, "inexact->exact", n => n.to_exact()?);
+
+fn expt(operands: Term) -> Result<Term, Error> {
+    let [base, exponent] = expect_args(operands, "expt")?;
+    number(&base)?.expt(number(&exponent)?).map(Term::from)
+}
+
+fn bools(operands: Term) -> Result<Vec<bool>, Error> {
+    operands.to_list()?.iter()
+        .map(|term| (term as &dyn TermAccess<bool>).try_access().copied())
+        .collect()
+}
+
+fn not(operands: Term) -> Result<Term, Error> {
+    let [b] = expect_args(operands, "not?")?;
+    Ok(Term::from(!*(&b as &dyn TermAccess<bool>).try_access()?))
+}
+
+fn and(operands: Term) -> Result<Term, Error> {
+    Ok(Term::from(bools(operands)?.into_iter().all(|b| b)))
+}
+
+fn or(operands: Term) -> Result<Term, Error> {
+    Ok(Term::from(bools(operands)?.into_iter().any(|b| b)))
+}
+
+/// Evaluate the operands of the operative `name` from left to right, and
+/// stop at the first one evaluated to `short_circuit`. The last operand is
+/// evaluated as a tail call.
+fn eval_until(ctx: &mut Context, operands: Term, env: &Env, name: &str, short_circuit: bool) -> Result<Step, Error> {
+    operands.to_list()?;
+    let Some(pair) = operands.as_pair() else {
+        return Ok(Step::Return(Term::from(!short_circuit)))
+    };
+    let rest = pair.cdr();
+    if !rest.is_nil() {
+        let data = Term::cons(Term::from(Symbol::from(name)), Term::cons(Term::from(short_circuit), rest));
+        ctx.push_resume(resume_until, data, env)?;
+    }
+    Ok(Step::Eval(pair.car(), env.clone()))
+}
+
+fn resume_until(ctx: &mut Context, value: Term, data: Term, env: &Env) -> Result<Step, Error> {
+    let name = data.as_pair().map(|pair| pair.car()).unwrap_or_default();
+    let name = (&name as &dyn TermAccess<Symbol>).try_access()?.clone();
+    let ([_, short_circuit], rest) = expect_at_least(data, name.as_ref())?;
+    let short_circuit = *(&short_circuit as &dyn TermAccess<bool>).try_access()?;
+    if *(&value as &dyn TermAccess<bool>).try_access()? == short_circuit {
+        return Ok(Step::Return(value))
+    }
+    eval_until(ctx, rest, env, name.as_ref(), short_circuit)
+}
+
+fn and_operative(ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    eval_until(ctx, operands, env, "$and?", false)
+}
+
+fn or_operative(ctx: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    eval_until(ctx, operands, env, "$or?", true)
+}
+
+fn eq(operands: Term) -> Result<Term, Error> {
+    let [a, b] = expect_args(operands, "eq?")?;
+    Ok(Term::from(a.is_eq(&b)))
+}
+
+fn equal(operands: Term) -> Result<Term, Error> {
+    let [a, b] = expect_args(operands, "equal?")?;
+    Ok(Term::from(a.equal(&b)))
+}
+
+macro_rules! type_predicate {
+    ($name: ident, $term: ident => $cond: expr) => {
+        /// Check whether all the operands satisfy the predicate.
+        fn $name(operands: Term) -> Result<Term, Error> {
+            Ok(Term::from(operands.to_list()?.iter().all(|$term| $cond)))
+        }
+    };
+}
+
+type_predicate!(is_boolean, term => (term as &dyn TermAccess<bool>).try_access().is_ok());
+type_predicate!(is_number, term => number(term).is_ok());
+type_predicate!(is_integer, term => number(term).is_ok_and(Number::is_integer));
+type_predicate!(is_rational, term => number(term).is_ok_and(|n| n.is_exact() || n.to_f64().is_finite()));
+type_predicate!(is_exact, term => number(term).is_ok_and(Number::is_exact));
+type_predicate!(is_inexact, term => number(term).is_ok_and(|n| !n.is_exact()));
+type_predicate!(is_string, term => (term as &dyn TermAccess<String>).try_access().is_ok());
+type_predicate!(is_char, term => (term as &dyn TermAccess<char>).try_access().is_ok());
+type_predicate!(is_symbol, term => (term as &dyn TermAccess<Symbol>).try_access().is_ok());
+type_predicate!(is_inert, term => *term == Term::inert());
+type_predicate!(is_ignore, term => term.is_ignore());
+type_predicate!(is_environment, term => (term as &dyn TermAccess<Env>).try_access().is_ok());
+type_predicate!(is_continuation, term => (term as &dyn TermAccess<Continuation>).try_access().is_ok());
+type_predicate!(is_combiner, term => term.is_combiner());
+type_predicate!(is_operative, term => term.is_combiner()
+    && (term as &dyn TermAccess<Applicative>).try_access().is_err());
+type_predicate!(is_applicative, term => (term as &dyn TermAccess<Applicative>).try_access().is_ok());
+type_predicate!(is_null, term => term.is_nil());
+type_predicate!(is_pair, term => term.is_pair());
+type_predicate!(is_vector, term => elements(term).is_ok());
+
+fn char_to_integer(operands: Term) -> Result<Term, Error> {
+    let [ch] = expect_args(operands, "char->integer")?;
+    Ok(Term::from(*(&ch as &dyn TermAccess<char>).try_access()? as i64))
+}
+
+fn integer_to_char(operands: Term) -> Result<Term, Error> {
+    let [n] = expect_args(operands, "integer->char")?;
+    let n = number(&n)?;
+    match n { Number::Integer(int) => int.to_u32(), _ => None }
+        .and_then(char::from_u32)
+        .map(Term::from)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidArithmetic)
+            .with_message(format!("{n} is not a valid Unicode scalar value.")))
+}
+
+fn print(text: std::fmt::Arguments) -> Result<Term, Error> {
+    let mut out = std::io::stdout();
+    out.write_fmt(text).and_then(|_| out.flush())
+        .map(|_| Term::inert())
+        .map_err(|err| Error::new(ErrorKind::IoFailure).with_message(err.to_string()))
+}
+
+fn display(operands: Term) -> Result<Term, Error> {
+    let [term] = expect_args(operands, "display")?;
+    print(format_args!("{term}"))
+}
+
+fn write(operands: Term) -> Result<Term, Error> {
+    let [term] = expect_args(operands, "write")?;
+    print(format_args!("{}", term.written()))
+}
+
+fn newline(operands: Term) -> Result<Term, Error> {
+    let [] = expect_args(operands, "newline")?;
+    print(format_args!("\n"))
+}
+
+fn list(operands: Term) -> Result<Term, Error> {
+    Ok(operands)
+}
+
+/// `(list* a b ... tail)` is like `list`, but the last operand becomes the
+/// tail of the list.
+fn list_star(operands: Term) -> Result<Term, Error> {
+    let mut terms = operands.to_list()?;
+    match terms.pop() {
+        Some(tail) => Ok(Term::list_with_tail(terms, tail)),
+        None => Err(Error::new(ErrorKind::TypeMismatch)
+            .with_message("'list*' expects at least 1 operand(s), but 0 were given.".to_string()))
+    }
+}
+
+fn cons(operands: Term) -> Result<Term, Error> {
+    let [car, cdr] = expect_args(operands, "cons")?;
+    Ok(Term::cons(car, cdr))
+}
+
+fn car(operands: Term) -> Result<Term, Error> {
+    let [pair] = expect_args(operands, "car")?;
+    Ok((&pair as &dyn TermAccess<Rc<Pair>>).try_access()?.car())
+}
+
+fn cdr(operands: Term) -> Result<Term, Error> {
+    let [pair] = expect_args(operands, "cdr")?;
+    Ok((&pair as &dyn TermAccess<Rc<Pair>>).try_access()?.cdr())
+}
+
+fn set_car(operands: Term) -> Result<Term, Error> {
+    let [pair, car] = expect_args(operands, "set-car!")?;
+    (&pair as &dyn TermAccess<Rc<Pair>>).try_access()?.set_car(car);
+    Ok(Term::inert())
+}
+
+fn set_cdr(operands: Term) -> Result<Term, Error> {
+    let [pair, cdr] = expect_args(operands, "set-cdr!")?;
+    (&pair as &dyn TermAccess<Rc<Pair>>).try_access()?.set_cdr(cdr);
+    Ok(Term::inert())
+}
+
+fn length(operands: Term) -> Result<Term, Error> {
+    let [list] = expect_args(operands, "length")?;
+    Ok(Term::from(list.to_list()?.len() as i64))
+}
+
+/// The lists are copied except the last one, which is shared as the tail
+/// of the result.
+fn append(operands: Term) -> Result<Term, Error> {
+    let mut lists = operands.to_list()?;
+    let tail = lists.pop().unwrap_or_default();
+    let mut terms = vec![];
+    for list in lists {
+        terms.extend(list.to_list()?);
+    }
+    Ok(Term::list_with_tail(terms, tail))
+}
+
+fn elements(term: &Term) -> Result<&Rc<Vec<Term>>, Error> {
+    (term as &dyn TermAccess<Rc<Vec<Term>>>).try_access()
+}
+
+fn vector(operands: Term) -> Result<Term, Error> {
+    Ok(Term::vector(operands.to_list()?))
+}
+
+fn vector_length(operands: Term) -> Result<Term, Error> {
+    let [vector] = expect_args(operands, "vector-length")?;
+    Ok(Term::from(elements(&vector)?.len() as i64))
+}
+
+fn vector_ref(operands: Term) -> Result<Term, Error> {
+    let [vector, k] = expect_args(operands, "vector-ref")?;
+    let (terms, k) = (elements(&vector)?, number(&k)?);
+    match k { Number::Integer(int) => int.to_usize(), _ => None }
+        .and_then(|i| terms.get(i).cloned())
+        .ok_or_else(|| Error::new(ErrorKind::TypeMismatch)
+            .with_message(format!("{k} is not a valid index of a vector of length {}.", terms.len())))
+}
+
+fn vector_to_list(operands: Term) -> Result<Term, Error> {
+    let [vector] = expect_args(operands, "vector->list")?;
+    Ok(Term::list(elements(&vector)?.iter().cloned()))
+}
+
+fn list_to_vector(operands: Term) -> Result<Term, Error> {
+    let [list] = expect_args(operands, "list->vector")?;
+    Ok(Term::vector(list.to_list()?))
+}
+
+/// `($quasiquote template)` results in the template, in which the operands
+/// of `unquote` are replaced by their values and those of `unquote-splicing`
+/// by the elements of their values. The quasiquotations can be nested, and
+/// only the unquotations of the outermost level are evaluated.
+fn quasiquote(_: &mut Context, operands: Term, env: &Env) -> Result<Step, Error> {
+    let [template] = expect_args(operands, "$quasiquote")?;
+    Ok(Step::Eval(expand_quasiquote(template, 1)?, env.clone()))
+}
+
+/// Expand the template at the level of nesting quasiquotations into the
+/// combinations constructing it. The primitives are placed in the
+/// combinations directly, so that they can't be shadowed by the bindings.
+fn expand_quasiquote(template: Term, level: usize) -> Result<Term, Error> {
+    let quoted = |term: Term| Term::list([Term::from(NativeFn::new("$quote", Pure(quote))), term]);
+    let applied = |name: &'static str, func: fn(Term) -> Result<Term, Error>, operands: [Term; 2]| {
+        let combiner = Term::from(Applicative::new(Term::from(NativeFn::new(name, Pure(func)))));
+        Term::list([combiner].into_iter().chain(operands))
+    };
+    match quasiquote_form(&template) {
+        Some(("unquote", operand)) if level == 1 => return Ok(operand),
+        Some(("unquote-splicing", _)) if level == 1 => return Err(Error::new(ErrorKind::InvalidSyntax)
+            .with_message("'unquote-splicing' can only be placed in a list.".to_string())),
+        Some((name, operand)) => {
+            let operand = expand_quasiquote(operand, if_or!(name == "$quasiquote", level + 1, level - 1))?;
+            return Ok(applied("list", list, [quoted(Term::from(Symbol::from(name))), operand]))
+        },
+        None => {}
+    }
+
+    // The tail of the list can be an unquotation as well, as in `(a . ,b)`.
+    let mut items = vec![];
+    let mut tail = template;
+    while let Some(pair) = tail.as_pair().cloned() {
+        if quasiquote_form(&tail).is_some() { break }
+        items.push(pair.car());
+        tail = pair.cdr();
+    }
+    if items.is_empty() { return Ok(quoted(tail)) }
+    let mut result = expand_quasiquote(tail, level)?;
+    for item in items.into_iter().rev() {
+        result = match quasiquote_form(&item) {
+            Some(("unquote-splicing", operand)) if level == 1 => applied("append", append, [operand, result]),
+            _ => applied("cons", cons, [expand_quasiquote(item, level)?, result])
+        };
+    }
+    Ok(result)
+}
+
+/// Destruct `(name operand)` if the name is `$quasiquote`, `unquote` or
+/// `unquote-splicing`.
+fn quasiquote_form(term: &Term) -> Option<(&'static str, Term)> {
+    let pair = term.as_pair()?;
+    let name = match &pair.car().value {
+        TermValue::Sym(symbol) => ["$quasiquote", "unquote", "unquote-splicing"].into_iter()
+            .find(|name| symbol.as_ref() == name)?,
+        _ => return None
+    };
+    let rest = pair.cdr();
+    let operand = rest.as_pair().filter(|rest| rest.cdr().is_nil())?.car();
+    Some((name, operand))
+}
@@ -1,7 +1,13 @@
 mod combiner;
 mod term;
+mod number;
 mod context;
+mod continuation;
+mod ground;
+mod library;
 
 pub use combiner::*;
 pub use term::*;
+pub use number::*;
 pub use context::*;
+pub use continuation::*;
@@ -0,0 +1,395 @@
+use std::cmp::Ordering;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{Euclid, One, Pow, Signed, ToPrimitive, Zero};
+
+use crate::if_or;
+use crate::error::{Error, ErrorKind};
+use crate::syntax::NumberLiteral;
+
+/// A number of the numeric tower. Exact integers and rationals are of
+/// arbitrary precision, and inexact reals are `f64`. An operation on
+/// exact numbers results in an exact number, unless it has no exact
+/// result, and any inexact operand makes the result inexact.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    Integer(BigInt),
+    /// An exact rational, which is never an integer.
+    Rational(BigRational),
+    Real(f64),
+}
+
+/// The most bits of the numerator or denominator `expt` computes for an
+/// exact result, which is 2 MiB.
+pub const MAX_EXACT_BITS: u64 = 1 << 24;
+
+fn division_by_zero(name: &str) -> Error {
+    Error::new(ErrorKind::InvalidArithmetic)
+        .with_message(format!("Division by zero occurred in '{name}'."))
+}
+
+impl Number {
+    pub fn is_exact(&self) -> bool {
+        !matches!(self, Number::Real(_))
+    }
+
+    /// Whether the value is an integer, inexact reals included.
+    pub fn is_integer(&self) -> bool {
+        match self {
+            Number::Integer(_) => true,
+            Number::Rational(_) => false,
+            Number::Real(real) => real.is_finite() && real.fract() == 0.0,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Integer(int) => int.is_zero(),
+            Number::Rational(_) => false,
+            Number::Real(real) => *real == 0.0,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Number::Integer(int) => int.is_negative(),
+            Number::Rational(rational) => rational.is_negative(),
+            Number::Real(real) => *real < 0.0,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Number::Integer(_) => "integer",
+            Number::Rational(_) => "rational",
+            Number::Real(_) => "real",
+        }
+    }
+
+    /// The exact value as a rational, or `None` for inexact reals.
+    fn to_rational(&self) -> Option<BigRational> {
+        match self {
+            Number::Integer(int) => Some(BigRational::from_integer(int.clone())),
+            Number::Rational(rational) => Some(rational.clone()),
+            Number::Real(_) => None,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Integer(int) => int.to_f64().unwrap_or(f64::NAN),
+            Number::Rational(rational) => rational.to_f64().unwrap_or(f64::NAN),
+            Number::Real(real) => *real,
+        }
+    }
+
+    pub fn to_inexact(&self) -> Number {
+        Number::Real(self.to_f64())
+    }
+
+    /// Convert to the exact number of the same value, which fails for
+    /// infinities and NaN.
+    pub fn to_exact(&self) -> Result<Number, Error> {
+        match self {
+            Number::Real(real) => BigRational::from_float(*real).map(Number::from)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidArithmetic)
+                    .with_message(format!("{self} has no exact representation."))),
+            exact => Ok(exact.clone()),
+        }
+    }
+
+    /// Apply the operation on the integers, rationals or reals, to which
+    /// both of the operands are converted.
+    fn binary(&self, other: &Number,
+        integer: fn(&BigInt, &BigInt) -> BigInt,
+        rational: fn(&BigRational, &BigRational) -> BigRational,
+        real: fn(f64, f64) -> f64) -> Number {
+        match (self, other) {
+            (Number::Integer(a), Number::Integer(b)) => Number::Integer(integer(a, b)),
+            (a, b) => match (a.to_rational(), b.to_rational()) {
+                (Some(a), Some(b)) => Number::from(rational(&a, &b)),
+                _ => Number::Real(real(a.to_f64(), b.to_f64())),
+            }
+        }
+    }
+
+    /// Divide by the other number, which must not be an exact zero.
+    pub fn div(&self, other: &Number) -> Result<Number, Error> {
+        if other.is_exact() && other.is_zero() { return Err(division_by_zero("/")) }
+        Ok(match (self.to_rational(), other.to_rational()) {
+            (Some(a), Some(b)) => Number::from(a / b),
+            _ => Number::Real(self.to_f64() / other.to_f64()),
+        })
+    }
+
+    /// The Euclidean quotient and remainder, where the remainder is never
+    /// negative. Both of the operands must be integers.
+    pub fn div_mod(&self, other: &Number, name: &str) -> Result<(Number, Number), Error> {
+        for n in [self, other] {
+            if !n.is_integer() {
+                return Err(Error::new(ErrorKind::TypeMismatch)
+                    .with_message(format!("'{name}' expects integers, but found {n}.")))
+            }
+        }
+        if other.is_zero() { return Err(division_by_zero(name)) }
+        Ok(match (self, other) {
+            (Number::Integer(a), Number::Integer(b)) =>
+                (Number::Integer(a.div_euclid(b)), Number::Integer(a.rem_euclid(b))),
+            (a, b) => {
+                let (a, b) = (a.to_f64(), b.to_f64());
+                (Number::Real(a.div_euclid(b)), Number::Real(a.rem_euclid(b)))
+            }
+        })
+    }
+
+    /// Compare the values, exactly unless an operand is an infinity or
+    /// NaN, which is unordered with any number.
+    pub fn compare(&self, other: &Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Integer(a), Number::Integer(b)) => Some(a.cmp(b)),
+            (Number::Real(a), Number::Real(b)) => a.partial_cmp(b),
+            (a, b) => match (a.to_exact(), b.to_exact()) {
+                (Ok(a), Ok(b)) => a.to_rational()?.partial_cmp(&b.to_rational()?),
+                _ => a.to_f64().partial_cmp(&b.to_f64()),
+            }
+        }
+    }
+
+    pub fn abs(&self) -> Number {
+        if_or!(self.is_negative(), -self, self.clone())
+    }
+
+    /// Round to an integer with the functions of rationals and reals.
+    fn round_with(&self, rational: fn(&BigRational) -> BigRational, real: fn(f64) -> f64) -> Number {
+        match self {
+            Number::Integer(_) => self.clone(),
+            Number::Rational(r) => Number::from(rational(r)),
+            Number::Real(r) => Number::Real(real(*r)),
+        }
+    }
+
+    pub fn floor(&self) -> Number { self.round_with(BigRational::floor, f64::floor) }
+
+    pub fn ceiling(&self) -> Number { self.round_with(BigRational::ceil, f64::ceil) }
+
+    pub fn truncate(&self) -> Number { self.round_with(BigRational::trunc, f64::trunc) }
+
+    /// Round to the nearest integer, and to the even one on ties.
+    pub fn round(&self) -> Number {
+        self.round_with(|r| {
+            let floor = r.floor();
+            let diff = r - &floor;
+            let half = BigRational::new(BigInt::one(), BigInt::from(2));
+            match diff.cmp(&half) {
+                Ordering::Less => floor,
+                Ordering::Greater => floor + BigRational::one(),
+                Ordering::Equal => if_or!(floor.to_integer().is_even(), floor, floor + BigRational::one()),
+            }
+        }, f64::round_ties_even)
+    }
+
+    /// Raise to the power, which is exact if the base is exact and the
+    /// exponent is an exact integer.
+    pub fn expt(&self, exponent: &Number) -> Result<Number, Error> {
+        let Number::Integer(exponent) = exponent else {
+            return Ok(Number::Real(self.to_f64().powf(exponent.to_f64())))
+        };
+        let Some(base) = self.to_rational() else {
+            return Ok(Number::Real(self.to_f64().powf(exponent.to_f64().unwrap_or(f64::NAN))))
+        };
+        if base.is_zero() && exponent.is_negative() { return Err(division_by_zero("expt")) }
+        // Only the trivial bases can be raised to a huge exponent.
+        if base.is_zero() || base.is_one() { return Ok(Number::from(base)) }
+        if -&base == BigRational::one() {
+            return Ok(Number::from(if_or!(exponent.is_even(), BigRational::one(), base)))
+        }
+        let Some(power) = exponent.abs().to_u32() else {
+            return Err(Error::new(ErrorKind::InvalidArithmetic)
+                .with_message(format!("The exponent {exponent} is too large.")))
+        };
+        // The size of the result is estimated before computing it.
+        let bits = base.numer().bits().max(base.denom().bits()).saturating_mul(power.into());
+        if bits > MAX_EXACT_BITS {
+            return Err(Error::new(ErrorKind::InvalidArithmetic)
+                .with_message(format!("The exact result of raising to {exponent} is too large, about {bits} bits.")))
+        }
+        let result = Pow::pow(base, power);
+        Ok(Number::from(if_or!(exponent.is_negative(), result.recip(), result)))
+    }
+
+    /// The square root, which is exact if the operand is the square of an
+    /// exact number.
+    pub fn sqrt(&self) -> Result<Number, Error> {
+        if self.is_negative() {
+            return Err(Error::new(ErrorKind::InvalidArithmetic)
+                .with_message(format!("The square root of {self} is not a real number.")))
+        }
+        if let Some(rational) = self.to_rational() {
+            let (numer, denom) = (rational.numer().sqrt(), rational.denom().sqrt());
+            if &(&numer * &numer) == rational.numer() && &(&denom * &denom) == rational.denom() {
+                return Ok(Number::from(BigRational::new(numer, denom)))
+            }
+        }
+        Ok(Number::Real(self.to_f64().sqrt()))
+    }
+
+    pub fn numerator(&self) -> Result<Number, Error> {
+        match self {
+            Number::Real(_) => self.to_exact()?.numerator().map(|n| n.to_inexact()),
+            exact => Ok(Number::Integer(exact.to_rational().unwrap_or_default().numer().clone())),
+        }
+    }
+
+    pub fn denominator(&self) -> Result<Number, Error> {
+        match self {
+            Number::Real(_) => self.to_exact()?.denominator().map(|n| n.to_inexact()),
+            exact => Ok(Number::Integer(exact.to_rational().unwrap_or_default().denom().clone())),
+        }
+    }
+}
+
+macro_rules! impl_op {
+    ($op: ident, $method: ident) => {
+        impl $op<&Number> for &Number {
+            type Output = Number;
+
+            fn $method(self, other: &Number) -> Number {
+                self.binary(other, |a, b| a.$method(b), |a, b| a.$method(b), |a, b| a.$method(b))
+            }
+        }
+    };
+}
+
+impl_op!(Add, add);
+impl_op!(Sub, sub);
+impl_op!(Mul, mul);
+
+impl Neg for &Number {
+    type Output = Number;
+
+    fn neg(self) -> Number {
+        match self {
+            Number::Integer(int) => Number::Integer(-int),
+            Number::Rational(rational) => Number::Rational(-rational),
+            Number::Real(real) => Number::Real(-real),
+        }
+    }
+}
+
+impl From<i64> for Number {
+    fn from(value: i64) -> Self { Number::Integer(BigInt::from(value)) }
+}
+
+impl From<BigInt> for Number {
+    fn from(value: BigInt) -> Self { Number::Integer(value) }
+}
+
+/// Rationals are normalized to integers when possible.
+impl From<BigRational> for Number {
+    fn from(value: BigRational) -> Self {
+        if_or!(value.is_integer(), Number::Integer(value.to_integer()), Number::Rational(value))
+    }
+}
+
+impl From<f64> for Number {
+    fn from(value: f64) -> Self { Number::Real(value) }
+}
+
+/// A rational literal with a zero denominator is rejected, which is never
+/// read by [`NumberLiteral::parse`].
+impl TryFrom<NumberLiteral> for Number {
+    type Error = Error;
+
+    fn try_from(literal: NumberLiteral) -> Result<Self, Error> {
+        let integer = |negative: bool, radix: u32, digits: &str| {
+            let int = BigInt::parse_bytes(digits.as_bytes(), radix).unwrap_or_default();
+            if_or!(negative, -int, int)
+        };
+        Ok(match literal {
+            NumberLiteral::Integer { negative, radix, digits } =>
+                Number::Integer(integer(negative, radix, &digits)),
+            NumberLiteral::Rational { negative, radix, numerator, denominator } => {
+                let denominator = integer(false, radix, &denominator);
+                if denominator.is_zero() {
+                    return Err(Error::new(ErrorKind::InvalidSyntax)
+                        .with_message("The denominator of a rational can't be zero.".to_string()))
+                }
+                Number::from(BigRational::new(integer(negative, radix, &numerator), denominator))
+            },
+            NumberLiteral::Decimal(decimal) => Number::Real(decimal.parse().unwrap_or(f64::NAN)),
+        })
+    }
+}
+
+/// Inexact reals are written to be read back as inexact, with a decimal
+/// point or an exponent, and `+inf.0`, `-inf.0` or `+nan.0`.
+impl std::fmt::Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Integer(int) => write!(f, "{int}"),
+            Number::Rational(rational) => write!(f, "{}/{}", rational.numer(), rational.denom()),
+            Number::Real(real) if real.is_nan() => write!(f, "+nan.0"),
+            Number::Real(real) if real.is_infinite() => write!(f, "{}inf.0", if_or!(*real > 0.0, "+", "-")),
+            Number::Real(real) => write!(f, "{real:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Number;
+    use crate::syntax::NumberLiteral;
+
+    fn number(literal: &str) -> Number {
+        Number::try_from(NumberLiteral::parse(literal).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn number_arithmetic() {
+        assert_eq!(&number("9223372036854775807") + &number("1"), number("9223372036854775808"));
+        assert_eq!(&number("1/2") + &number("1/2"), number("1"));
+        assert_eq!(&number("1/3") * &number("0.5"), Number::Real(1.0 / 6.0));
+        assert_eq!(number("1").div(&number("3")).unwrap().to_string(), "1/3");
+        assert_eq!(number("1.0").div(&number("0.0")).unwrap().to_string(), "+inf.0");
+        assert!(number("1").div(&number("0")).is_err());
+        assert_eq!(number("2").expt(&number("100")).unwrap().to_string(), "1267650600228229401496703205376");
+        assert_eq!(number("2/3").expt(&number("-2")).unwrap().to_string(), "9/4");
+        assert_eq!(number("2").expt(&number("4000000000")).unwrap_err().kind(), crate::error::ErrorKind::InvalidArithmetic);
+        assert_eq!(number("1/3").expt(&number("-10000000")).unwrap_err().kind(), crate::error::ErrorKind::InvalidArithmetic);
+        assert_eq!(number("2").expt(&number("1000000")).unwrap().to_rational().unwrap().numer().bits(), 1_000_001);
+        assert_eq!(number("2.0").expt(&number("4000000000")).unwrap(), Number::Real(f64::INFINITY));
+        assert_eq!(number("4/9").sqrt().unwrap(), number("2/3"));
+        assert_eq!(number("2").sqrt().unwrap(), Number::Real(2f64.sqrt()));
+    }
+
+    #[test]
+    fn number_rounding() {
+        let rounded = |literal: &str| {
+            let n = number(literal);
+            [n.floor(), n.ceiling(), n.truncate(), n.round()].map(|n| n.to_string())
+        };
+        assert_eq!(rounded("7/2"), ["3", "4", "3", "4"]);
+        assert_eq!(rounded("-5/2"), ["-3", "-2", "-2", "-2"]);
+        assert_eq!(rounded("2.5"), ["2.0", "3.0", "2.0", "2.0"]);
+    }
+
+    #[test]
+    fn number_comparison() {
+        use std::cmp::Ordering::*;
+        assert_eq!(number("1").compare(&number("1.0")), Some(Equal));
+        assert_eq!(number("1/3").compare(&number("0.3333")), Some(Greater));
+        assert_eq!(number("99999999999999999999").compare(&number("+inf.0")), Some(Less));
+        assert_eq!(number("1").compare(&number("+nan.0")), None);
+        assert_ne!(number("1"), number("1.0"));
+    }
+
+    #[test]
+    fn number_from_literal() {
+        assert_eq!(number("#x-1F/2").to_string(), "-31/2");
+        let zero = NumberLiteral::Rational { negative: false, radix: 10, numerator: "1".into(), denominator: "0".into() };
+        assert_eq!(Number::try_from(zero).unwrap_err().kind(), crate::error::ErrorKind::InvalidSyntax);
+    }
+}
@@ -1,45 +1,213 @@
 use std::cell::RefCell;
-use std::collections::LinkedList;
+use std::collections::HashSet;
+use std::rc::Rc;
 
+use crate::{if_or, seq};
 use crate::error::{Error, ErrorKind};
-use crate::syntax::Symbol;
+use crate::parser::Span;
+use crate::syntax::{Symbol, CHAR_NAMES};
 
-use super::combiner::NativeFn;
+use super::combiner::{Applicative, Escape, NativeFn, Operative};
+use super::context::Env;
+use super::continuation::Continuation;
+use super::number::Number;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Clone)]
 pub struct Term {
-    has_value: bool,
-    pub(crate) sub_terms: LinkedList<Term>,
     pub(crate) value: TermValue,
-    pub(crate) value_ref: RefCell<TermValue>
+    /// Where the term is read from, which locates the runtime errors.
+    span: Option<Rc<Span>>
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Terms are compared as `equal?` does, regardless of their source locations.
+impl PartialEq for Term {
+    fn eq(&self, other: &Self) -> bool { self.equal(other) }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum TermValue {
+    Applicative(Applicative),
     Bool(BooleanValue),
-    Int(i64),
+    Char(char),
+    Continuation(Continuation),
+    Env(Env),
+    Escape(Escape),
+    Nil,
+    Number(Number),
+    Operative(Rc<Operative>),
+    Pair(Rc<Pair>),
     PrimitiveFn(NativeFn),
     Str(String),
     Sym(Symbol),
     Unit(UnitValue),
+    Vector(Rc<Vec<Term>>),
+}
+
+/// A mutable cons cell, shared between all the terms referring to it.
+pub struct Pair {
+    car: RefCell<Term>,
+    cdr: RefCell<Term>
+}
+
+/// Pairs are compared by identity, the lists are compared by their
+/// elements with [`Term::equal`].
+impl PartialEq for Pair {
+    fn eq(&self, other: &Self) -> bool { std::ptr::eq(self, other) }
+}
+
+impl Pair {
+    pub fn car(&self) -> Term { self.car.borrow().clone() }
+
+    pub fn cdr(&self) -> Term { self.cdr.borrow().clone() }
+
+    pub fn set_car(&self, term: Term) { *self.car.borrow_mut() = term }
+
+    pub fn set_cdr(&self, term: Term) { *self.cdr.borrow_mut() = term }
 }
 
 impl Term {
+    /// Construct the empty list `()`.
     pub fn new() -> Self {
-        Self {
-            has_value: false,
-            sub_terms: LinkedList::new(),
-            value: TermValue::Unit(UnitValue::Ignore),
-            value_ref: RefCell::new(TermValue::Unit(UnitValue::Ignore))
+        Self { value: TermValue::Nil, span: None }
+    }
+
+    pub fn span(&self) -> Option<&Rc<Span>> { self.span.as_ref() }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        seq!(self.span = Some(Rc::new(span)), self)
+    }
+
+    pub fn cons(car: Term, cdr: Term) -> Self {
+        Term::from(Rc::new(Pair { car: RefCell::new(car), cdr: RefCell::new(cdr) }))
+    }
+
+    /// Construct a proper list from the given terms.
+    pub fn list<I: IntoIterator<Item = Term>>(terms: I) -> Self {
+        Term::list_with_tail(terms, Term::new())
+    }
+
+    /// Construct a list from the given terms ending with `tail` instead
+    /// of `()`, which is improper if `tail` is not a list.
+    pub fn list_with_tail<I: IntoIterator<Item = Term>>(terms: I, tail: Term) -> Self {
+        let terms: Vec<Term> = terms.into_iter().collect();
+        terms.into_iter().rev().fold(tail, |cdr, car| Term::cons(car, cdr))
+    }
+
+    pub fn vector(terms: Vec<Term>) -> Self {
+        Term::from(Rc::new(terms))
+    }
+
+    pub fn inert() -> Self {
+        Term::from(UnitValue::Inert)
+    }
+
+    pub fn ignore() -> Self {
+        Term::from(UnitValue::Ignore)
+    }
+
+    /// Whether the term is the empty list `()`.
+    pub fn is_nil(&self) -> bool {
+        self.value == TermValue::Nil
+    }
+
+    pub fn is_pair(&self) -> bool {
+        matches!(self.value, TermValue::Pair(_))
+    }
+
+    pub fn is_ignore(&self) -> bool {
+        self.value == TermValue::Unit(UnitValue::Ignore)
+    }
+
+    pub fn is_combiner(&self) -> bool {
+        matches!(self.value,
+            TermValue::Applicative(_) | TermValue::Escape(_) | TermValue::Operative(_) | TermValue::PrimitiveFn(_))
+    }
+
+    pub fn as_pair(&self) -> Option<&Rc<Pair>> {
+        match &self.value {
+            TermValue::Pair(pair) => Some(pair),
+            _ => None
         }
     }
 
-    pub fn is_branch(&self) -> bool {
-        !self.sub_terms.is_empty()
+    /// Collect the elements of a proper list, improper and cyclic lists
+    /// are rejected.
+    pub fn to_list(&self) -> Result<Vec<Term>, Error> {
+        let mut terms = vec![];
+        let mut current = self.clone();
+        // The hare moves two steps each time the tortoise moves one,
+        // they can only meet in a cyclic list.
+        let mut tortoise = self.clone();
+        while let Some(pair) = current.as_pair().cloned() {
+            terms.push(pair.car());
+            current = pair.cdr();
+            if terms.len() % 2 == 0 {
+                tortoise = tortoise.as_pair().map(|pair| pair.cdr()).unwrap_or_default();
+                if current.is_eq(&tortoise) && current.is_pair() {
+                    return Err(Error::new(ErrorKind::TypeMismatch)
+                        .with_message("Expected a finite list, but found a cyclic one.".to_string()))
+                }
+            }
+        }
+        if !current.is_nil() {
+            return Err(Error::new(ErrorKind::TypeMismatch)
+                .with_message(format!("Expected a list, but found an improper list ending with {}.", current.type_name())))
+        }
+        Ok(terms)
+    }
+
+    /// Compare by identity for pairs, vectors, environments and combiners, and by
+    /// value for the others.
+    pub fn is_eq(&self, other: &Term) -> bool {
+        match (&self.value, &other.value) {
+            (TermValue::Pair(a), TermValue::Pair(b)) => Rc::ptr_eq(a, b),
+            (TermValue::Vector(a), TermValue::Vector(b)) => Rc::ptr_eq(a, b),
+            (TermValue::Applicative(a), TermValue::Applicative(b)) => a.unwrap().is_eq(&b.unwrap()),
+            (a, b) => a == b
+        }
+    }
+
+    /// Compare pairs and vectors by their elements and the others as `is_eq`
+    /// does. The elements are compared from an explicit stack, and a pair of
+    /// pairs met again is taken as equal to stop at cycles.
+    pub fn equal(&self, other: &Term) -> bool {
+        let mut visited: HashSet<(*const Pair, *const Pair)> = HashSet::new();
+        let mut stack = vec![(self.clone(), other.clone())];
+        while let Some((a, b)) = stack.pop() {
+            match (&a.value, &b.value) {
+                (TermValue::Pair(x), TermValue::Pair(y)) => {
+                    if Rc::ptr_eq(x, y) || !visited.insert((Rc::as_ptr(x), Rc::as_ptr(y))) { continue }
+                    stack.extend([(x.cdr(), y.cdr()), (x.car(), y.car())]);
+                },
+                (TermValue::Vector(x), TermValue::Vector(y)) => {
+                    if Rc::ptr_eq(x, y) { continue }
+                    if x.len() != y.len() { return false }
+                    stack.extend(x.iter().cloned().zip(y.iter().cloned()).rev());
+                },
+                _ => if !a.is_eq(&b) { return false }
+            }
+        }
+        true
     }
 
-    pub fn len(&self) -> usize {
-        self.sub_terms.len()
+    /// Name of the value's type, used by diagnostics.
+    pub fn type_name(&self) -> &'static str {
+        match self.value {
+            TermValue::Applicative(_) => "applicative",
+            TermValue::Bool(_) => "boolean",
+            TermValue::Char(_) => "character",
+            TermValue::Continuation(_) => "continuation",
+            TermValue::Env(_) => "environment",
+            TermValue::Nil => "null",
+            TermValue::Number(ref n) => n.type_name(),
+            TermValue::Escape(_) | TermValue::Operative(_) | TermValue::PrimitiveFn(_) => "operative",
+            TermValue::Pair(_) => "pair",
+            TermValue::Str(_) => "string",
+            TermValue::Sym(_) => "symbol",
+            TermValue::Unit(UnitValue::Ignore) => "ignore",
+            TermValue::Unit(UnitValue::Inert) => "inert",
+            TermValue::Vector(_) => "vector",
+        }
     }
 }
 
@@ -49,12 +217,224 @@ impl Default for Term {
     }
 }
 
+impl Term {
+    /// Wrap the term to print its written representation, in which
+    /// strings are quoted and escaped.
+    pub fn written(&self) -> Written<'_> { Written(self) }
+
+    /// Write the term without recursion, the terms nested in lists and
+    /// vectors are written from an explicit stack. A pair met again inside
+    /// itself is written as `...`.
+    fn fmt_with(&self, f: &mut std::fmt::Formatter<'_>, written: bool) -> std::fmt::Result {
+        enum Piece {
+            Term(Term),
+            /// The rest of a list from the pair.
+            Rest(Rc<Pair>),
+            Text(&'static str),
+            /// The end of a list, whose pairs are removed from the path.
+            Leave(usize)
+        }
+        // The pairs of the lists being written, from the outermost one.
+        let mut path: Vec<*const Pair> = vec![];
+        let mut on_path: HashSet<*const Pair> = HashSet::new();
+        let mut stack = vec![Piece::Term(self.clone())];
+        while let Some(piece) = stack.pop() {
+            let pair = match piece {
+                Piece::Term(term) => match &term.value {
+                    TermValue::Pair(pair) if on_path.contains(&Rc::as_ptr(pair)) => seq!(f.write_str("...")?, continue),
+                    TermValue::Pair(pair) => seq!(write!(f, "(")?, stack.push(Piece::Leave(path.len())), pair.clone()),
+                    TermValue::Str(s) if written => seq!(write!(f, "\"{}\"", escape_str(s))?, continue),
+                    TermValue::Char(ch) if written => seq!(write!(f, "{}", escape_char(*ch))?, continue),
+                    TermValue::Vector(terms) => {
+                        write!(f, "[")?;
+                        stack.push(Piece::Text("]"));
+                        for (i, term) in terms.iter().enumerate().rev() {
+                            stack.push(Piece::Term(term.clone()));
+                            if i > 0 { stack.push(Piece::Text(" ")) }
+                        }
+                        continue
+                    },
+                    value => seq!(write!(f, "{value}")?, continue)
+                },
+                Piece::Rest(pair) => pair,
+                Piece::Text(text) => seq!(f.write_str(text)?, continue),
+                Piece::Leave(len) => {
+                    for pair in path.drain(len..) { on_path.remove(&pair); }
+                    continue
+                }
+            };
+            path.push(Rc::as_ptr(&pair));
+            on_path.insert(Rc::as_ptr(&pair));
+            let cdr = pair.cdr();
+            match cdr.as_pair() {
+                Some(next) if on_path.contains(&Rc::as_ptr(next)) => stack.push(Piece::Text(" ...)")),
+                Some(next) => stack.extend([Piece::Rest(next.clone()), Piece::Text(" ")]),
+                None if cdr.is_nil() => stack.push(Piece::Text(")")),
+                None => stack.extend([Piece::Text(")"), Piece::Term(cdr), Piece::Text(" . ")])
+            }
+            stack.push(Piece::Term(pair.car()));
+        }
+        Ok(())
+    }
+}
+
+/// A piece of the `Debug` output of a term, which is written as the derived
+/// implementation does, but from an explicit stack instead of recursion.
+/// A pair met again inside itself is written as `...`.
+enum DebugPiece {
+    Term(Term),
+    Span(Option<Rc<Span>>),
+    Text(&'static str),
+    /// The end of a pair, which is removed from the path.
+    Leave(*const Pair)
+}
+
+impl DebugPiece {
+    /// Write the pieces, `on_path` are the pairs being written.
+    fn write(
+        mut stack: Vec<DebugPiece>,
+        mut on_path: HashSet<*const Pair>,
+        f: &mut std::fmt::Formatter<'_>
+    ) -> std::fmt::Result {
+        while let Some(piece) = stack.pop() {
+            let term = match piece {
+                DebugPiece::Term(term) => term,
+                DebugPiece::Span(span) => seq!(write!(f, "{span:?}")?, continue),
+                DebugPiece::Text(text) => seq!(f.write_str(text)?, continue),
+                DebugPiece::Leave(pair) => seq!(on_path.remove(&pair), continue)
+            };
+            if term.as_pair().is_some_and(|pair| on_path.contains(&Rc::as_ptr(pair))) {
+                seq!(f.write_str("...")?, continue)
+            }
+            f.write_str("Term { value: ")?;
+            stack.extend([DebugPiece::Text(" }"), DebugPiece::Span(term.span.clone()), DebugPiece::Text(", span: ")]);
+            match &term.value {
+                TermValue::Pair(pair) => {
+                    f.write_str("Pair(")?;
+                    on_path.insert(Rc::as_ptr(pair));
+                    stack.extend([DebugPiece::Leave(Rc::as_ptr(pair)), DebugPiece::Text(")")]);
+                    stack.extend(Self::pair(pair));
+                },
+                TermValue::Vector(terms) => {
+                    f.write_str("Vector([")?;
+                    stack.push(DebugPiece::Text("])"));
+                    for (i, term) in terms.iter().enumerate().rev() {
+                        stack.push(DebugPiece::Term(term.clone()));
+                        if i > 0 { stack.push(DebugPiece::Text(", ")) }
+                    }
+                },
+                value => write!(f, "{value:?}")?
+            }
+        }
+        Ok(())
+    }
+
+    /// The pieces of the pair after `Pair { car: `, in the reverse order.
+    fn pair(pair: &Pair) -> [DebugPiece; 5] {
+        [DebugPiece::Text(" }"), DebugPiece::Term(pair.cdr()), DebugPiece::Text(", cdr: "),
+            DebugPiece::Term(pair.car()), DebugPiece::Text("Pair { car: ")]
+    }
+}
+
+impl std::fmt::Debug for Term {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        DebugPiece::write(vec![DebugPiece::Term(self.clone())], HashSet::new(), f)
+    }
+}
+
+impl std::fmt::Debug for Pair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        DebugPiece::write(DebugPiece::pair(self).into(), HashSet::from([self as *const Pair]), f)
+    }
+}
+
+/// The terms nested in pairs and vectors owned only by the term are moved
+/// to a stack and dropped one by one, instead of being dropped recursively.
+impl Drop for Term {
+    fn drop(&mut self) {
+        // Only the terms nesting others are moved, to spare the stack for
+        // the pairs and vectors of atoms.
+        fn detach(term: &mut Term, stack: &mut Vec<Term>) {
+            let nesting = |term: &Term| matches!(term.value, TermValue::Pair(_) | TermValue::Vector(_));
+            match &mut term.value {
+                TermValue::Pair(pair) => if let Some(pair) = Rc::get_mut(pair) {
+                    let cells = [pair.car.get_mut(), pair.cdr.get_mut()];
+                    stack.extend(cells.into_iter().filter(|term| nesting(term)).map(core::mem::take))
+                },
+                TermValue::Vector(terms) => if let Some(terms) = Rc::get_mut(terms) {
+                    stack.extend(terms.drain(..).filter(nesting))
+                },
+                _ => ()
+            }
+        }
+        let mut stack = vec![];
+        detach(self, &mut stack);
+        while let Some(mut term) = stack.pop() {
+            detach(&mut term, &mut stack);
+        }
+    }
+}
+
+/// Escape the string to be placed between double quotes.
+pub fn escape_str(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            ch if ch.is_control() => escaped.push_str(&format!("\\x{:x};", ch as u32)),
+            ch => escaped.push(ch)
+        }
+    }
+    escaped
+}
+
+/// Write the character as a literal, `#\\name` for the named ones and
+/// `#\\x..` for other control characters.
+pub fn escape_char(ch: char) -> String {
+    match CHAR_NAMES.iter().find(|(_, named)| *named == ch) {
+        Some((name, _)) => format!("#\\{name}"),
+        None if ch.is_control() => format!("#\\x{:x}", ch as u32),
+        None => format!("#\\{ch}")
+    }
+}
+
+pub struct Written<'a>(&'a Term);
+
+impl std::fmt::Display for Written<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt_with(f, true)
+    }
+}
+
 impl std::fmt::Display for Term {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if !self.has_value {
-            write!(f, "{:?}", self.sub_terms)
-        } else {
-            write!(f, "{:?}", self.value)
+        self.fmt_with(f, false)
+    }
+}
+
+impl std::fmt::Display for TermValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TermValue::Applicative(_) => write!(f, "#[applicative]"),
+            TermValue::Bool(b) => write!(f, "{}", if_or!(*b, "#t", "#f")),
+            TermValue::Char(ch) => write!(f, "{ch}"),
+            TermValue::Continuation(_) => write!(f, "#[continuation]"),
+            TermValue::Env(_) => write!(f, "#[environment]"),
+            TermValue::Escape(_) => write!(f, "#[operative continuation]"),
+            TermValue::Nil => write!(f, "()"),
+            TermValue::Number(n) => write!(f, "{n}"),
+            TermValue::Operative(_) => write!(f, "#[operative]"),
+            TermValue::Pair(_) => write!(f, "#[pair]"),
+            TermValue::PrimitiveFn(native) => write!(f, "#[operative {}]", native.name()),
+            TermValue::Str(s) => write!(f, "{s}"),
+            TermValue::Sym(symbol) => write!(f, "{symbol}"),
+            TermValue::Unit(UnitValue::Ignore) => write!(f, "#ignore"),
+            TermValue::Unit(UnitValue::Inert) => write!(f, "#inert"),
+            TermValue::Vector(_) => write!(f, "#[vector]"),
         }
     }
 }
@@ -79,7 +459,7 @@ pub trait TermAccess<T>:
     Access<T> + AccessMut<T> + TryAccess<T> + TryAccessMut<T> {}
 
 macro_rules! impl_access {
-    ($ty: ty, $ty_id: ident) => {
+    ($ty: ty, $ty_id: ident, $name: literal) => {
         impl Access<$ty> for Term {
             fn access(&self) -> &$ty {
                 match self.value {
@@ -102,17 +482,19 @@ macro_rules! impl_access {
             fn try_access(&self) -> Result<&$ty, Error> {
                 match self.value {
                     TermValue::$ty_id(ref val) => Ok(val),
-                    // TODO: Add messages.
-                    _ => Err(Error::new(ErrorKind::TypeMismatch))
+                    _ => Err(Error::new(ErrorKind::TypeMismatch)
+                        .with_message(format!("Expected {}, but found {}.", $name, self.type_name())))
                 }
             }
         }
 
         impl TryAccessMut<$ty> for Term {
             fn try_access_mut(&mut self) -> Result<&mut $ty, Error> {
+                let name = self.type_name();
                 match self.value {
                     TermValue::$ty_id(ref mut val) => Ok(val),
-                    _ => Err(Error::new(ErrorKind::TypeMismatch))
+                    _ => Err(Error::new(ErrorKind::TypeMismatch)
+                        .with_message(format!("Expected {}, but found {}.", $name, name)))
                 }
             }
         }
@@ -121,25 +503,35 @@ macro_rules! impl_access {
 
         impl From<$ty> for Term {
             fn from(value: $ty) -> Term {
-                let mut term = Term::new();
-                term.has_value = true;
-                term.value = TermValue::$ty_id(value);
-                term
+                Term { value: TermValue::$ty_id(value), span: None }
             }
         }
     };
 }
 
+impl From<i64> for Term {
+    fn from(value: i64) -> Term { Term::from(Number::from(value)) }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnitValue {
-    Ignore
+    Ignore,
+    Inert
 }
 
 type BooleanValue = bool;
 
-impl_access!(BooleanValue, Bool);
-impl_access!(i64, Int);
-impl_access!(NativeFn, PrimitiveFn);
-impl_access!(UnitValue, Unit);
-impl_access!(String, Str);
-impl_access!(Symbol, Sym);
+impl_access!(Applicative, Applicative, "applicative");
+impl_access!(BooleanValue, Bool, "boolean");
+impl_access!(char, Char, "character");
+impl_access!(Continuation, Continuation, "continuation");
+impl_access!(Env, Env, "environment");
+impl_access!(Escape, Escape, "operative");
+impl_access!(Number, Number, "number");
+impl_access!(Rc<Operative>, Operative, "operative");
+impl_access!(Rc<Pair>, Pair, "pair");
+impl_access!(NativeFn, PrimitiveFn, "operative");
+impl_access!(UnitValue, Unit, "unit");
+impl_access!(String, Str, "string");
+impl_access!(Symbol, Sym, "symbol");
+impl_access!(Rc<Vec<Term>>, Vector, "vector");
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::seq;
+use crate::cst::{CstElement, CstKind, CstNode};
+use crate::error::Error;
+use crate::parser::{Delimiter, SrcInfo, TokenKind};
+
+/// An element of the source kept by the formatter, along with the trivia
+/// between the data.
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    /// An atom or a block comment kept as its source text, after the quote
+    /// prefixes and datum comments glued to it.
+    Atom { prefix: String, text: String },
+    Seq { prefix: String, delimiter: Delimiter, items: Vec<Item> },
+    /// A line comment, which is trailing if it's on the same line as the
+    /// datum before it.
+    Comment { text: String, trailing: bool },
+    /// One or more blank lines between the items.
+    Blank
+}
+
+impl Item {
+    fn is_datum(&self) -> bool {
+        matches!(self, Item::Atom { .. } | Item::Seq { .. })
+    }
+}
+
+/// The nested items are moved to a stack and dropped one by one, instead
+/// of being dropped recursively.
+impl Drop for Item {
+    fn drop(&mut self) {
+        let mut stack = vec![];
+        if let Item::Seq { items, .. } = self { stack.append(items) }
+        while let Some(mut item) = stack.pop() {
+            if let Item::Seq { items, .. } = &mut item { stack.append(items) }
+        }
+    }
+}
+
+/// A step of writing the items. The nested items are written from an
+/// explicit stack of the steps instead of recursive calls, so that they
+/// can be nested to any depth.
+enum Task<'a> {
+    /// Write the items on their own lines at the column.
+    Items(&'a [Item], usize),
+    /// Render the item at the column of the output.
+    Item(&'a Item),
+    Char(char)
+}
+
+/// The formatted text, along with the column at its end.
+#[derive(Default)]
+struct Output {
+    text: String,
+    col: usize
+}
+
+impl Output {
+    fn push(&mut self, text: &str) {
+        match text.rsplit_once('\n') {
+            Some((_, last)) => self.col = last.width(),
+            None => self.col += text.width()
+        }
+        self.text.push_str(text)
+    }
+}
+
+/// Format the source of Thesis by re-indenting the forms, the comments
+/// and blank lines between them are kept.
+///
+/// A form fitting in the width is printed on a single line. Otherwise the
+/// operands of a call are aligned with the first one, except for the forms
+/// with special indentation, which keep the given number of operands on
+/// the first line and indent the rest as the body. The other sequences
+/// align their elements with the first one.
+///
+/// A sequence is closed right after its last datum, the comments after it
+/// are moved after the sequence, where a trailing one stays on the same
+/// line as the closing delimiter.
+#[derive(Debug, Clone)]
+pub struct SourceFormatter {
+    width: usize,
+    indent: usize,
+    /// Numbers of the operands kept on the first line of special forms.
+    special: HashMap<String, usize>
+}
+
+impl Default for SourceFormatter {
+    fn default() -> Self {
+        Self::new()
+            .with_special("$vau", 2)
+            .with_special("$lambda", 1)
+            .with_special("$let", 1)
+            .with_special("$let*", 1)
+            .with_special("$letrec", 1)
+            .with_special("$define!", 1)
+            .with_special("$cond", 0)
+            .with_special("$sequence", 0)
+    }
+}
+
+impl SourceFormatter {
+    /// Construct a formatter of 80 columns without special forms.
+    pub fn new() -> Self {
+        Self { width: 80, indent: 2, special: HashMap::new() }
+    }
+
+    pub fn with_width(mut self, width: usize) -> Self {
+        seq!(self.width = width, self)
+    }
+
+    /// The indentation of the bodies relative to their opening delimiters.
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        seq!(self.indent = indent, self)
+    }
+
+    /// Indent the body of the form after the `distinguished` operands.
+    pub fn with_special(mut self, name: &str, distinguished: usize) -> Self {
+        seq!(self.special.insert(name.to_string(), distinguished), self)
+    }
+
+    /// Format the source, which must be read without syntax errors.
+    pub fn format(&self, src: &SrcInfo) -> Result<String, Vec<Error>> {
+        let mut out = self.write(&Self::read(&CstNode::try_parse(src)?));
+        if !out.is_empty() { out.push('\n') }
+        Ok(out)
+    }
+
+    /// Read the concrete syntax tree into items, the trivia tokens are kept
+    /// as comments and blank lines.
+    fn read(cst: &CstNode) -> Vec<Item> {
+        /// A node of the tree being read.
+        struct Frame<'a> {
+            children: std::slice::Iter<'a, CstElement>,
+            /// The sequence read from the node, which is none for the root
+            /// and the prefixed data, whose items belong to the enclosing
+            /// sequence.
+            seq: Option<Item>
+        }
+        fn current<'a>(stack: &'a mut [Frame<'_>], items: &'a mut Vec<Item>) -> &'a mut Vec<Item> {
+            match stack.iter_mut().rev().find_map(|frame| frame.seq.as_mut()) {
+                Some(Item::Seq { items, .. }) => items,
+                _ => items
+            }
+        }
+        let mut stack = vec![Frame { children: cst.children().iter(), seq: None }];
+        let mut items = vec![];
+        let mut prefix = String::new();
+        // Whether a line break is placed before the token.
+        let mut newline = true;
+        while let Some(frame) = stack.last_mut() {
+            let token = match frame.children.next() {
+                Some(CstElement::Token(token)) => token,
+                Some(CstElement::Node(node)) => {
+                    let seq = match node.kind() {
+                        CstKind::Sequence(delimiter) =>
+                            Some(Item::Seq { prefix: core::mem::take(&mut prefix), delimiter, items: vec![] }),
+                        _ => None
+                    };
+                    seq!(stack.push(Frame { children: node.children().iter(), seq }), continue)
+                },
+                None => {
+                    // A sequence is closed right after its last datum.
+                    let Some(mut seq) = stack.pop().and_then(|frame| frame.seq) else { continue };
+                    let Item::Seq { items: nested, .. } = &mut seq else { continue };
+                    let rest = nested.split_off(nested.iter().rposition(Item::is_datum).map_or(0, |last| last + 1));
+                    let current = current(&mut stack, &mut items);
+                    current.push(seq);
+                    seq!(current.extend(rest.into_iter().filter(|item| *item != Item::Blank)), continue)
+                }
+            };
+            let text = token.text();
+            match token.kind() {
+                TokenKind::Whitespace => {
+                    let current = current(&mut stack, &mut items);
+                    let breaks = text.matches('\n').count();
+                    if breaks > 1 && current.last().is_some_and(|last| *last != Item::Blank) {
+                        current.push(Item::Blank)
+                    }
+                    newline |= breaks > 0;
+                    continue
+                },
+                TokenKind::Comment if text.starts_with(';') => {
+                    let current = current(&mut stack, &mut items);
+                    let trailing = !newline && current.last().is_some_and(Item::is_datum);
+                    current.push(Item::Comment { text: text.trim_end().to_string(), trailing })
+                },
+                TokenKind::Quote(_) | TokenKind::DatumComment => prefix.push_str(text),
+                // The sequences are read from their nodes.
+                TokenKind::Open(_) | TokenKind::Close(_) => (),
+                _ => {
+                    let atom = Item::Atom { prefix: core::mem::take(&mut prefix), text: text.to_string() };
+                    current(&mut stack, &mut items).push(atom)
+                }
+            }
+            newline = false;
+        }
+        if items.last().is_some_and(|last| *last == Item::Blank) { items.pop(); }
+        items
+    }
+
+    /// Write the items on their own lines, trailing comments are kept on
+    /// the lines before them.
+    fn write(&self, items: &[Item]) -> String {
+        let mut out = Output::default();
+        let mut tasks = vec![Task::Items(items, 0)];
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Items([], _) => (),
+                Task::Items([item, rest @ ..], col) => {
+                    tasks.push(Task::Items(rest, col));
+                    match item {
+                        Item::Comment { text, trailing: true } if !out.text.is_empty() => seq!(out.push(" "), out.push(text)),
+                        Item::Blank => out.push("\n"),
+                        item => {
+                            if !out.text.is_empty() { seq!(out.push("\n"), out.push(&" ".repeat(col))) }
+                            tasks.push(Task::Item(item))
+                        }
+                    }
+                },
+                Task::Item(item) => self.render(item, &mut out, &mut tasks),
+                Task::Char(ch) => out.push(ch.encode_utf8(&mut [0; 4]))
+            }
+        }
+        out.text
+    }
+
+    /// Render the item starting at the column of the output. The nested
+    /// items are left as the tasks after it, the lines after the first one
+    /// are indented.
+    fn render<'a>(&self, item: &'a Item, out: &mut Output, tasks: &mut Vec<Task<'a>>) {
+        let col = out.col;
+        if let Some(flat) = self.width.checked_sub(col).and_then(|width| Self::flat(item, width)) {
+            return out.push(&flat)
+        }
+        let (prefix, delimiter, items) = match item {
+            Item::Seq { prefix, delimiter, items } => (prefix, *delimiter, items),
+            // Atoms are kept as they are, even if they're too wide.
+            Item::Atom { prefix, text } => return seq!(out.push(prefix), out.push(text)),
+            Item::Comment { text, .. } => return out.push(text),
+            Item::Blank => return
+        };
+        let opening = col + prefix.width();
+        // The number of the items on the first line and the column of the
+        // others.
+        let (head, body) = match items.first() {
+            Some(Item::Atom { prefix, text })
+                if prefix.is_empty() && delimiter == Delimiter::Paren && TokenKind::of_atom(text) == TokenKind::Symbol =>
+                match self.special.get(text) {
+                    Some(&distinguished) =>
+                        (1 + items[1..].iter().take(distinguished).take_while(|item| item.is_datum()).count(),
+                            opening + self.indent),
+                    None if items.get(1).is_some_and(Item::is_datum) => (2, opening + 1 + text.width() + 1),
+                    None => (1, opening + self.indent)
+                },
+            Some(first) if first.is_datum() => (1, opening + 1),
+            _ => (0, opening + 1)
+        };
+        seq!(out.push(prefix), out.push(delimiter.open().encode_utf8(&mut [0; 4])));
+        tasks.extend([Task::Char(delimiter.close()), Task::Items(&items[head..], body)]);
+        for (i, item) in items[..head].iter().enumerate().rev() {
+            tasks.push(Task::Item(item));
+            if i > 0 { tasks.push(Task::Char(' ')) }
+        }
+    }
+
+    /// The item on a single line, if it can be within the width.
+    fn flat(item: &Item, width: usize) -> Option<String> {
+        let mut out = String::new();
+        // The sequences being written, with the rest of their items and
+        // their closing delimiters.
+        let mut stack: Vec<(std::iter::Enumerate<std::slice::Iter<'_, Item>>, char)> = vec![];
+        let mut next = Some(item);
+        loop {
+            match next.take() {
+                Some(Item::Atom { prefix, text }) if !text.contains('\n') => seq!(out.push_str(prefix), out.push_str(text)),
+                Some(Item::Seq { prefix, delimiter, items }) => {
+                    seq!(out.push_str(prefix), out.push(delimiter.open()));
+                    stack.push((items.iter().enumerate(), delimiter.close()))
+                },
+                Some(_) => return None,
+                None => ()
+            }
+            if out.width() > width { return None }
+            let Some((items, close)) = stack.last_mut() else { break };
+            match items.next() {
+                Some((i, item)) => seq!(if i > 0 { out.push(' ') }, next = Some(item)),
+                None => seq!(out.push(*close), stack.pop(), ())
+            }
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::SrcInfo;
+    use super::SourceFormatter;
+
+    fn format(formatter: &SourceFormatter, source: &str) -> String {
+        formatter.format(&SrcInfo::new("test", source)).unwrap()
+    }
+
+    #[test]
+    fn format_forms() {
+        let formatter = SourceFormatter::default().with_width(30);
+        assert_eq!(format(&formatter, "  (f   x\n  y)  "), "(f x y)\n");
+        assert_eq!(format(&formatter, "($define! f ($lambda (x) (+ x 1) (* x 2) (- x 3)))"), concat!(
+            "($define! f\n",
+            "  ($lambda (x)\n",
+            "    (+ x 1)\n",
+            "    (* x 2)\n",
+            "    (- x 3)))\n"
+        ));
+        assert_eq!(format(&formatter, "($vau (x y) env (eval x env) (eval y env))"), concat!(
+            "($vau (x y) env\n",
+            "  (eval x env)\n",
+            "  (eval y env))\n"
+        ));
+        assert_eq!(format(&formatter, "(list 'alpha 'beta [gamma delta] \"epsilon\")"), concat!(
+            "(list 'alpha\n",
+            "      'beta\n",
+            "      [gamma delta]\n",
+            "      \"epsilon\")\n"
+        ));
+        assert_eq!(format(&formatter, "[(alpha beta gamma) (delta epsilon zeta)]"), concat!(
+            "[(alpha beta gamma)\n",
+            " (delta epsilon zeta)]\n"
+        ));
+        assert_eq!(format(&SourceFormatter::new().with_width(10), "($let ((x 1)) x)"), "($let ((x 1))\n      x)\n");
+    }
+
+    #[test]
+    fn format_trivia() {
+        let formatter = SourceFormatter::default();
+        let source = concat!(
+            ";; Header.\n",
+            "\n\n\n",
+            "($define! f ; trailing\n",
+            "   ($lambda (x)\n",
+            "      ; leading\n",
+            "      #;(ignored form)\n",
+            "\n",
+            "      #| block |# x))\n",
+            "(g \"multi\n  line\" 1 ; last\n)\n",
+            "\n"
+        );
+        assert_eq!(format(&formatter, source), concat!(
+            ";; Header.\n",
+            "\n",
+            "($define! f ; trailing\n",
+            "  ($lambda (x)\n",
+            "    ; leading\n",
+            "    #;(ignored form)\n",
+            "\n",
+            "    #| block |#\n",
+            "    x))\n",
+            "(g \"multi\n  line\"\n   1) ; last\n"
+        ));
+        assert_eq!(format(&formatter, "(f\n ;c\n)"), "(f)\n;c\n");
+        assert_eq!(format(&formatter, "(a (b c ; x\n) ; y\n)"), "(a (b c)) ; x\n; y\n");
+        assert_eq!(format(&formatter, "' ; c\n(a #;#;x y ' z)"), "; c\n'(a #;#;x y 'z)\n");
+        assert_eq!(format(&formatter, ""), "");
+    }
+
+    #[test]
+    fn format_idempotent() {
+        let formatter = SourceFormatter::default().with_width(24);
+        let source = concat!(
+            "; Comment\n($define! fact ($lambda (n) ($if (<=? n 1) 1 (* n (fact (- n 1))))))\n\n",
+            "($cond ((null? x) '()) (#t (cons (car x) (f (cdr x)))))"
+        );
+        let formatted = format(&formatter, source);
+        assert_eq!(format(&formatter, &formatted), formatted);
+        let read = |source: &str| {
+            let mut parser = crate::parser::SyntacticParser::new(crate::share!(SrcInfo::new("test", source)));
+            parser.try_parse().unwrap();
+            parser.tree()
+        };
+        assert_eq!(read(&formatted), read(source));
+        assert!(formatter.format(&SrcInfo::new("test", "(a")).is_err());
+    }
+
+    #[test]
+    fn format_deep_nesting() {
+        let formatter = SourceFormatter::default().with_width(40);
+        let calls = |depth: usize| format!("{}x{}", "(f x ".repeat(depth), ")".repeat(depth));
+        assert_eq!(format(&formatter, &calls(1000)).lines().count(), 1001);
+        let depth = 100_000;
+        let nested = format!("{}x{}", "([".repeat(depth / 2), "])".repeat(depth / 2));
+        assert_eq!(format(&formatter, &nested), nested + "\n");
+    }
+}
@@ -1,46 +1,71 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use ariadne::Source;
-
+use crate::seq;
+use crate::error::{Error, ErrorKind};
 use crate::parser::*;
-use crate::evaluation::Context;
+use crate::evaluation::{Context, Term};
+use crate::syntax::{Node, NodeValue};
 
 #[derive(Debug)]
 pub struct Interpreter {
     interactive: bool,
     root_ctx: Context,
-    src: Rc<RefCell<SrcInfo>>
+    src: Rc<RefCell<SrcInfo>>,
+    transformer: InfixTransformer
+}
+
+impl Default for Interpreter {
+    fn default() -> Self { Self::new() }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
         let src_info = SrcInfo::new("", "");
         let rc = Rc::new(RefCell::new(src_info));
-        Self { interactive: true, root_ctx: Context::new(rc.clone()), src: rc.clone() }
+        Self {
+            interactive: true,
+            root_ctx: Context::new(rc.clone()),
+            src: rc.clone(),
+            transformer: InfixTransformer::default()
+        }
     }
 
+    /// Evaluate the top-level forms of the unit in order, the results
+    /// are printed in interactive mode.
     pub fn read(&mut self, unit: &mut String) {
         let mut parser = SyntacticParser::new(self.src.clone());
         self.src.borrow_mut().text = core::mem::take(unit);
-        let _ = parser.try_parse().is_err_and(|err| {
-            err.report
-                .unwrap()
-                .finish()
-                .print((self.src.borrow().id.clone(), Source::from(&self.src.borrow().text)))
-                .unwrap();
-            true
-        });
-        let _ = self.root_ctx.eval(parser.reset().into())
-            .is_err_and(|err| {
-                err.report
-                    .unwrap()
-                    .finish()
-                    .print((self.src.borrow().id.clone(), Source::from(&self.src.borrow().text)))
-                    .unwrap();
-            if !self.interactive { std::process::exit(1); }
-            false
-        });
+        let forms = parser.try_parse()
+            .and_then(|_| self.transformer.transform(&self.src.borrow(), parser.reset()).map_err(|err| vec![err]))
+            .and_then(|tree| top_level_forms(tree).map_err(|err| vec![err]));
+        let forms = match forms {
+            Ok(forms) => forms,
+            Err(errors) => {
+                for err in errors {
+                    err.print(&self.src.borrow());
+                }
+                if !self.interactive { std::process::exit(1); }
+                return;
+            }
+        };
+        for form in forms {
+            match Term::try_from(form).and_then(|term| self.root_ctx.eval(term)) {
+                Ok(result) => if self.interactive { println!("{result}") },
+                Err(err) => {
+                    err.print(&self.src.borrow());
+                    if !self.interactive { std::process::exit(1); }
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Evaluate the script as a whole, and exit on the first error.
+    pub fn run_script(&mut self, id: &str, mut content: String) {
+        self.interactive = false;
+        self.src.borrow_mut().id = id.to_string();
+        self.read(&mut content);
     }
 
     // TODO: Add history
@@ -51,8 +76,13 @@ impl Interpreter {
         loop {
             let mut line = String::new();
             print!("> "); // Print prompt
-            stdout().flush().unwrap();
-            stdin().read_line(&mut line).unwrap();
+            let _ = stdout().flush();
+            match stdin().read_line(&mut line) {
+                Ok(0) => std::process::exit(0),
+                Ok(_) => {},
+                // The line is skipped if it's not valid UTF-8.
+                Err(err) => seq!(println!("Error: {err}"), continue)
+            }
             line = line.trim().into();
 
             if line == "exit" { std::process::exit(0) }
@@ -61,3 +91,457 @@ impl Interpreter {
         }
     }
 }
+
+/// Read and evaluate the source without reporting, and return the results
+/// of the top-level forms or the errors stopping it. The evaluation is
+/// aborted after `max_steps` steps, so that any input can be passed.
+pub fn eval_source(id: &str, text: &str, max_steps: usize) -> Result<Vec<Term>, Vec<Error>> {
+    let src = Rc::new(RefCell::new(SrcInfo::new(id, text)));
+    let mut parser = SyntacticParser::new(src.clone());
+    parser.try_parse()?;
+    let tree = InfixTransformer::default().transform(&src.borrow(), parser.reset()).map_err(|err| vec![err])?;
+    let forms = top_level_forms(tree).map_err(|err| vec![err])?;
+    let mut ctx = Context::new(src);
+    ctx.set_max_steps(Some(max_steps));
+    forms.into_iter()
+        .map(|form| Term::try_from(form).and_then(|term| ctx.eval(term)))
+        .collect::<Result<_, _>>()
+        .map_err(|err| vec![err])
+}
+
+/// The forms of the tree read from a source, which is always a list of
+/// them unless the tree is transformed into another node.
+fn top_level_forms(tree: Node) -> Result<Vec<Node>, Error> {
+    match tree.into_parts() {
+        (NodeValue::List(forms), _) => Ok(forms),
+        (value, _) => Err(Error::new(ErrorKind::InvalidSyntax)
+            .with_message(format!("Expected a list of top-level forms, but found {}.", Node::from(value))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::share;
+    use crate::error::{Error, ErrorKind};
+    use crate::evaluation::{Context, Term};
+    use crate::parser::{InfixTransformer, SrcInfo, SyntacticParser};
+    use super::eval_source;
+    use crate::syntax::{NodeValue, Symbol};
+
+    fn eval_str(source: &str) -> Vec<String> {
+        let src = share!(SrcInfo::new("test", source));
+        let mut parser = SyntacticParser::new(src.clone());
+        parser.try_parse().unwrap();
+        let tree = InfixTransformer::default().transform(&src.borrow(), parser.tree()).unwrap();
+        let mut ctx = Context::new(src);
+        let (NodeValue::List(forms), _) = tree.into_parts() else { unreachable!() };
+        forms.into_iter()
+            .map(|form| ctx.eval(Term::try_from(form).unwrap()).unwrap().to_string())
+            .collect()
+    }
+
+    /// Evaluate the first form of the source, which has to raise an error.
+    fn eval_err(source: &str) -> Error {
+        let src = share!(SrcInfo::new("test", source));
+        let mut parser = SyntacticParser::new(src.clone());
+        parser.try_parse().unwrap();
+        let (NodeValue::List(mut forms), _) = parser.tree().into_parts() else { unreachable!() };
+        let mut ctx = Context::new(src);
+        Term::try_from(forms.remove(0)).and_then(|term| ctx.eval(term)).unwrap_err()
+    }
+
+    #[test]
+    fn eval_operatives() {
+        assert_eq!(eval_str("(($vau (x) #ignore x) y)"), vec!["y"]);
+        assert_eq!(eval_str("(($vau x #ignore x) a b)"), vec!["(a b)"]);
+        assert_eq!(eval_str("($define! quote ($vau (x) #ignore x)) (quote (a b))"), vec!["#inert", "(a b)"]);
+        assert_eq!(eval_str("(($vau (x) e (eval x e)) #t)"), vec!["#t"]);
+    }
+
+    #[test]
+    fn eval_applicatives() {
+        assert_eq!(eval_str("(($lambda (x y) y) #t #f)"), vec!["#f"]);
+        assert_eq!(eval_str("((wrap ($vau (x) #ignore x)) #inert)"), vec!["#inert"]);
+        assert_eq!(eval_str("((unwrap ($lambda (x) x)) y)"), vec!["y"]);
+        assert_eq!(eval_str("($define! f ($lambda () ($if #f #f #t))) (f)"), vec!["#inert", "#t"]);
+    }
+
+    #[test]
+    fn eval_environments() {
+        assert_eq!(eval_str("($define! e (make-environment)) ($binds? e $if) ($set! e x #t) ($binds? e x)"),
+            vec!["#inert", "#f", "#inert", "#t"]);
+        assert_eq!(eval_str("($define! e (make-environment (get-current-environment))) ($binds? e $if)"),
+            vec!["#inert", "#t"]);
+        assert_eq!(eval_str("($define! x #t) ($let ((x #f)) x) x"), vec!["#inert", "#f", "#t"]);
+        assert_eq!(eval_str("($define! $if #f) $if"), vec!["#inert", "#f"]);
+    }
+
+    #[test]
+    fn eval_closures() {
+        assert_eq!(eval_str(r#"
+            ($define! make-box ($lambda (x) ($lambda () x)))
+            ($define! box (make-box #t))
+            ($define! x #f)
+            (box)
+        "#).last().unwrap(), "#t");
+        assert_eq!(eval_str(r#"
+            ($define! toggle ($let ((state #f))
+                ($define! self (get-current-environment))
+                ($lambda () ($set! self state ($if state #f #t)) state)))
+            (toggle)
+        "#).last().unwrap(), "#t");
+    }
+
+    #[test]
+    fn eval_arithmetic() {
+        assert_eq!(eval_str("(+) (+ 1 2 3) (- 5) (- 10 2 3) (* 2 3 4) (/ 7 2)"),
+            vec!["0", "6", "-5", "5", "24", "7/2"]);
+        assert_eq!(eval_str("(div (- 7) 2) (mod (- 7) 2) (mod 7 (- 2))"), vec!["-4", "1", "1"]);
+        assert_eq!(eval_str("(=? 1 1 1) (<? 1 2 2) (<=? 1 2 2) (>? 3 2 1) (>=? 1 2)"),
+            vec!["#t", "#f", "#t", "#t", "#f"]);
+    }
+
+    #[test]
+    fn eval_arithmetic_errors() {
+        let eval = |source: &str| eval_err(source).kind();
+        assert_eq!(eval("(/ 1 0)"), ErrorKind::InvalidArithmetic);
+        assert_eq!(eval("(mod 1 0)"), ErrorKind::InvalidArithmetic);
+        assert_eq!(eval("(sqrt -4)"), ErrorKind::InvalidArithmetic);
+        assert_eq!(eval("(div 1/2 1)"), ErrorKind::TypeMismatch);
+        assert_eq!(eval("(+ 1 #t)"), ErrorKind::TypeMismatch);
+        assert_eq!(eval("(not? 1 2)"), ErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn eval_error_locations() {
+        let locate = |source: &str| {
+            let err = eval_err(source);
+            err.location().map(|span| &source[span.range()]).unwrap_or_default().to_string()
+        };
+        assert_eq!(locate("(car (cons 1 undefined))"), "undefined");
+        assert_eq!(locate("(list 1 (+ 1 #t) 3)"), "(+ 1 #t)");
+        assert_eq!(locate("($if (list (/ 1 0)) 1 2)"), "(/ 1 0)");
+        assert_eq!(locate("($if (list 1) 1 2)"), "($if (list 1) 1 2)");
+        assert_eq!(locate("(($lambda (x) (car x)) 1)"), "(car x)");
+        // Terms constructed at runtime have no location of their own.
+        assert_eq!(locate("(eval (list car 1) (get-current-environment))"), "(eval (list car 1) (get-current-environment))");
+    }
+
+    #[test]
+    fn eval_number_literals() {
+        assert_eq!(eval_str("-7 +5 #x-1F #b101 #o17 1_000_000 (div -7 2)"),
+            vec!["-7", "5", "-31", "5", "15", "1000000", "-4"]);
+    }
+
+    #[test]
+    fn eval_numeric_tower() {
+        assert_eq!(eval_str("(* 9223372036854775807 2) (- -9223372036854775808 1) (expt 3 50)"),
+            vec!["18446744073709551614", "-9223372036854775809", "717897987691852588770249"]);
+        assert_eq!(eval_str("(+ 1/3 2/3) (/ 6 4) (* 1/2 4) (/ 2) (numerator 6/4) (denominator 6/4)"),
+            vec!["1", "3/2", "2", "1/2", "3", "2"]);
+        assert_eq!(eval_str("(+ 1/2 0.5) (* 2 1.5) (/ 1.0 0.0) (exact->inexact 1/4) (inexact->exact 0.25) (- 0.5)"),
+            vec!["1.0", "3.0", "+inf.0", "0.25", "1/4", "-0.5"]);
+        assert_eq!(eval_str("(floor -7/2) (ceiling 7/2) (round 5/2) (round 2.5) (round 3.5) (truncate -1.5)"),
+            vec!["-4", "4", "2", "2.0", "4.0", "-1.0"]);
+        assert_eq!(eval_str("(sqrt 16) (sqrt 1/4) (sqrt 2.25) (expt 2 -2) (expt 2.0 3) (expt 4 1/2)"),
+            vec!["4", "1/2", "1.5", "1/4", "8.0", "2.0"]);
+        assert_eq!(eval_str("(=? 1 1.0 2/2) (<? 1/3 0.34 1) (equal? 1 1.0) (max 1 2.0) (min 1 2) (abs -1/2)"),
+            vec!["#t", "#t", "#f", "2.0", "1", "1/2"]);
+        assert_eq!(eval_str("(integer? 2.0 4/2) (rational? 1/2 +inf.0) (exact? 1/2) (inexact? 1.0) (number? 1.5)"),
+            vec!["#t", "#f", "#t", "#t", "#t"]);
+    }
+
+    #[test]
+    fn eval_logic_and_predicates() {
+        assert_eq!(eval_str("(not? #t) (and? #t #f) (or? #f #t) (and?) ($and? #f undefined) ($or? #t undefined)"),
+            vec!["#f", "#f", "#t", "#t", "#f", "#t"]);
+        assert_eq!(eval_str("(eq? 1 1) (equal? (list 1 2) (list 1 2)) (equal? 1 #t)"), vec!["#t", "#t", "#f"]);
+        assert_eq!(eval_str("(integer? 1 2) (boolean? #t 1) (symbol? ($vau (x) #ignore x)) (null? ()) (pair? ())"),
+            vec!["#t", "#f", "#f", "#t", "#f"]);
+        assert_eq!(eval_str("(applicative? list) (operative? $if) (operative? list) (environment? (get-current-environment))"),
+            vec!["#t", "#t", "#f", "#t"]);
+    }
+
+    #[test]
+    fn eval_lists() {
+        assert_eq!(eval_str("(list 1 2) (cons 1 (list 2)) (car (list 1 2)) (cdr (list 1 2)) (length (list 1 2))"),
+            vec!["(1 2)", "(1 2)", "1", "(2)", "2"]);
+        assert_eq!(eval_str("(append (list 1) () (list 2 3)) (list \"a\" 1)"), vec!["(1 2 3)", "(a 1)"]);
+    }
+
+    #[test]
+    fn eval_pairs() {
+        assert_eq!(eval_str("(cons 1 2) (list* 1 2 (list 3)) (list* 1) (cdr (cons 1 2))"),
+            vec!["(1 . 2)", "(1 2 3)", "1", "2"]);
+        assert_eq!(eval_str("($define! x (list 1 2)) (set-car! x 3) (set-cdr! (cdr x) 4) x"),
+            vec!["#inert", "#inert", "#inert", "(3 2 . 4)"]);
+        assert_eq!(eval_str("($define! x (list 1)) ($define! y (append (list 0) x)) (set-car! x 2) y"),
+            vec!["#inert", "#inert", "#inert", "(0 2)"]);
+        assert_eq!(eval_str("($define! x (list 1)) (eq? x x) (eq? x (list 1)) (equal? x (list 1))"),
+            vec!["#inert", "#t", "#f", "#t"]);
+        assert_eq!(eval_str("($define! x (list 1 2)) (set-cdr! (cdr x) x) x (pair? x)"),
+            vec!["#inert", "#inert", "(1 2 ...)", "#t"]);
+        assert_eq!(eval_str("($define! x (list 1 2)) (set-car! x x) x (list x x) (set-car! (cdr x) (list x))")[2..4],
+            ["(... 2)", "((... 2) (... 2))"]);
+        assert_eq!(eval_str("($define! x (list 1 2)) (set-car! (cdr x) (list x)) x")[2], "(1 (...))");
+    }
+
+    #[test]
+    fn eval_structural_equality() {
+        assert_eq!(eval_str("($define! x (list 1)) (set-cdr! x x) (equal? x x)")[2], "#t");
+        let cycles = "($define! x (list 1 2)) (set-cdr! (cdr x) x) ($define! y (list 1 2 1 2)) (set-cdr! (cdr (cdr (cdr y))) y)";
+        assert_eq!(eval_str(&format!("{cycles} (equal? x y) (set-car! y 3) (equal? x y)"))[4..], ["#t", "#inert", "#f"]);
+        // Lists of 2^18 elements, built by doubling.
+        let long = "($define! x (list 1)) ($define! y (list 1))".to_string()
+            + &"($define! x (append x x)) ($define! y (append y y))".repeat(18);
+        assert_eq!(eval_str(&format!("{long} (length x) (equal? x y) (equal? x (cdr y))"))[38..], ["262144", "#t", "#f"]);
+    }
+
+    #[test]
+    fn eval_code_as_data() {
+        assert_eq!(eval_str("(eval (list + 1 2) (get-current-environment))"), vec!["3"]);
+        assert_eq!(eval_str("($define! $quote ($vau (x) #ignore x)) (car ($quote (f x)))"), vec!["#inert", "f"]);
+    }
+
+    #[test]
+    fn eval_quotations() {
+        assert_eq!(eval_str("'(a b) ($quote x) (car '(f x)) ''a"), vec!["(a b)", "x", "f", "($quote a)"]);
+        assert_eq!(eval_str("($define! x 2) `(1 ,x ,@(list 3 4) 5) `(,@'() a ,@'(b))").last().unwrap(), "(a b)");
+        assert_eq!(eval_str("($define! x 2) `(1 ,x ,@(list 3 4) 5)")[1], "(1 2 3 4 5)");
+        assert_eq!(eval_str("(($lambda (x) `(x ,x (,x))) 5) `a `()"), vec!["(x 5 (5))", "a", "()"]);
+        // Only the unquotations of the outermost level are evaluated.
+        assert_eq!(eval_str("($define! x 2) `(a `(b ,(c ,x)))")[1], "(a ($quasiquote (b (unquote (c 2)))))");
+        assert_eq!(eval_str("($define! x 2) `(a `(b ,,x))")[1], "(a ($quasiquote (b (unquote 2))))");
+        // The expansion doesn't depend on the bindings of the environment.
+        assert_eq!(eval_str("($define! cons 1) ($define! x 2) `(a ,x)")[2], "(a 2)");
+    }
+
+    #[test]
+    fn eval_dotted_lists() {
+        assert_eq!(eval_str("'(a . b) '(a . (b c)) (cdr '((a . 1) (b . 2)))"), vec!["(a . b)", "(a b c)", "((b . 2))"]);
+        assert_eq!(eval_str("(($lambda (x . rest) (list x rest)) 1 2 3)"), vec!["(1 (2 3))"]);
+        assert_eq!(eval_str("($define! x 2) `(a . ,x)")[1], "(a . 2)");
+    }
+
+    #[test]
+    fn eval_vectors() {
+        assert_eq!(eval_str("[1 (a b) \"c\"] (vector 1 (+ 1 1)) []"), vec!["[1 (a b) c]", "[1 2]", "[]"]);
+        assert_eq!(eval_str("(vector-length [a b c]) (vector-ref [a b c] 1) (vector->list [a b]) (list->vector '(1))"),
+            vec!["3", "b", "(a b)", "[1]"]);
+        assert_eq!(eval_str("(vector? [] '[a]) (vector? '(a)) (equal? [1 [2]] (vector 1 [2])) (eq? [1] [1])"),
+            vec!["#t", "#f", "#t", "#f"]);
+    }
+
+    #[test]
+    fn eval_vector_errors() {
+        assert_eq!(eval_err("(vector-ref [a] 1)").kind(), ErrorKind::TypeMismatch);
+        assert_eq!(eval_err("(vector-ref '(a) 0)").kind(), ErrorKind::TypeMismatch);
+        let err = eval_err("(f {1 + 2})");
+        assert_eq!(err.message(), "Curly braces are reserved for infix expressions.");
+        assert_eq!(err.span(), 3..10);
+    }
+
+    #[test]
+    fn eval_infix_expressions() {
+        assert_eq!(eval_str("{1 + 2 * 3} {{1 + 2} * 3} {2 ^ 10} {- 5} {and #t} {or #f}"),
+            vec!["7", "9", "1024", "-5", "#t", "#f"]);
+        assert_eq!(eval_str("($define! x 3) ($define! y 4) {x > 0 and y < 10} {x = y or {x + 1} = y}")[2..],
+            vec!["#t", "#t"]);
+        assert_eq!(eval_str("($define! f ($lambda (x y) {x * y})) {f(2 3) + f(4 5)} (car '{a + b})")[1..],
+            vec!["26", "+"]);
+    }
+
+    #[test]
+    fn eval_quotation_errors() {
+        let eval = |source: &str| eval_err(source).kind();
+        assert_eq!(eval("`,@'(a)"), ErrorKind::InvalidSyntax);
+        assert_eq!(eval("`(a ,@1)"), ErrorKind::TypeMismatch);
+        assert_eq!(eval("($quote a b)"), ErrorKind::TypeMismatch);
+        assert_eq!(eval(",a"), ErrorKind::FreeIdentifier);
+    }
+
+    #[test]
+    fn eval_hostile_inputs() {
+        // Regressions found by the fuzzer, the cyclic results have to be
+        // compared and written without running away.
+        let results = |source: &str| eval_source("test", source, 10_000).unwrap()
+            .into_iter().map(|term| term.to_string()).collect::<Vec<_>>();
+        let cycles = "($define! x (list 1 2)) (set-cdr! (cdr x) x) (set-car! x x) (equal? x x) (equal? x (list x 2)) x";
+        assert_eq!(results(cycles)[3..], ["#t", "#f", "(... 2 ...)"]);
+        let self_cycles = "($define! y (list 1)) (set-car! y y) (set-cdr! y y) (equal? y (cons y y)) (list y [y])";
+        assert_eq!(results(self_cycles)[3..], ["#t", "((... ...) [y])"]);
+        let long_lists = "($define! a (list 1)) ($define! b (list 1))".to_string()
+            + &"($define! a (append a a)) ($define! b (append b b))".repeat(16) + "(equal? a b) (equal? a (cdr b))";
+        assert_eq!(results(&long_lists)[34..], ["#t", "#f"]);
+    }
+
+    #[test]
+    fn eval_tail_calls() {
+        assert_eq!(eval_str(r#"
+            ($define! loop ($lambda (n) ($if (=? n 0) done (loop (- n 1)))))
+            ($define! done #t)
+            (loop 100000)
+        "#).last().unwrap(), "#t");
+        assert_eq!(eval_str(r#"
+            ($define! loop ($lambda (n)
+                ($cond ((=? n 0) #t)
+                       (#t ($sequence #inert (loop (- n 1)))))))
+            (loop 100000)
+        "#).last().unwrap(), "#t");
+        assert_eq!(eval_str(r#"
+            ($define! even? ($lambda (n) ($if (=? n 0) #t (odd? (- n 1)))))
+            ($define! odd? ($lambda (n) ($and? (not? (=? n 0)) (even? (- n 1)))))
+            (even? 10001)
+        "#).last().unwrap(), "#f");
+    }
+
+    #[test]
+    fn eval_deep_recursion() {
+        assert_eq!(eval_str(r#"
+            ($define! count ($lambda (n) ($if (=? n 0) 0 (+ 1 (count (- n 1))))))
+            (count 100000)
+        "#).last().unwrap(), "100000");
+    }
+
+    #[test]
+    fn eval_recursion_limit() {
+        let src = share!(SrcInfo::new("test", "($define! f ($lambda (n) (+ 1 (f n)))) (f 0)"));
+        let mut parser = SyntacticParser::new(src.clone());
+        parser.try_parse().unwrap();
+        let mut ctx = Context::new(src);
+        ctx.set_max_depth(10000);
+        let (NodeValue::List(forms), _) = parser.tree().into_parts() else { unreachable!() };
+        let mut results = forms.into_iter().map(|form| ctx.eval(Term::try_from(form).unwrap()));
+        assert!(results.next().unwrap().is_ok());
+        assert_eq!(results.next().unwrap().unwrap_err().kind(), ErrorKind::RecursionLimit);
+        assert_eq!(ctx.eval(Term::from(1)).unwrap(), Term::from(1));
+    }
+
+    #[test]
+    fn eval_apply() {
+        assert_eq!(eval_str("(apply + (list 1 2)) (apply list 1) (apply ($lambda x x) (list 1))"),
+            vec!["3", "1", "(1)"]);
+        assert_eq!(eval_str("($cond (#f 1) (#t 2 3)) ($cond) ($or? #f) ($and?)"), vec!["3", "#inert", "#f", "#t"]);
+    }
+
+    #[test]
+    fn eval_continuations() {
+        assert_eq!(eval_str("(call/cc ($lambda (k) 1)) (+ 1 (call/cc ($lambda (k) (+ 10 (apply (continuation->applicative k) 2)))))"),
+            vec!["1", "3"]);
+        assert_eq!(eval_str("(call/cc ($lambda (k) (continuation? k))) (continuation? call/cc) (call/cc ($lambda (k) k))"),
+            vec!["#t", "#f", "#[continuation]"]);
+        assert_eq!(eval_str(r#"
+            ($define! env (get-current-environment))
+            ($define! count 0)
+            ($define! k #inert)
+            ($sequence
+                ($define! v (call/cc ($lambda (c) ($set! env k c) 0)))
+                ($set! env count (+ count 1))
+                ($if (<? count 3) (apply (continuation->applicative k) count) v))
+        "#).last().unwrap(), "2");
+        assert_eq!(eval_str(r#"
+            (call/cc ($lambda (k)
+                (apply (continuation->applicative (extend-continuation k ($lambda (x) (* x 10)))) (list 4))))
+        "#), vec!["40"]);
+    }
+
+    #[test]
+    fn eval_guarded_continuations() {
+        // An exit guard intercepts the escape from its extent.
+        assert_eq!(eval_str(r#"
+            (call/cc ($lambda (top)
+                ($let ((g (guard-continuation () top (list (list top ($lambda (v divert) (cons 1 v)))))))
+                    (apply (continuation->applicative (extend-continuation g
+                        ($lambda () (apply (continuation->applicative top) (list 2))))) ()))))
+        "#), vec!["(1 2)"]);
+        // An entry guard intercepts the value passed into its extent.
+        assert_eq!(eval_str(r#"
+            (call/cc ($lambda (top)
+                ($let ((g (guard-continuation (list (list top ($lambda (v divert) (list (+ v 1))))) top ())))
+                    (apply (continuation->applicative (extend-continuation g ($lambda (x) (* x 10)))) 4))))
+        "#), vec!["50"]);
+        // The interceptor may divert the pass to the outer continuation.
+        assert_eq!(eval_str(r#"
+            (call/cc ($lambda (top)
+                ($let ((g (guard-continuation (list (list top ($lambda (v divert) (apply divert 7)))) top ())))
+                    (apply (continuation->applicative (extend-continuation g ($lambda (x) (* x 10)))) 4))))
+        "#), vec!["7"]);
+        // Guards not selected by the destination are left alone.
+        assert_eq!(eval_str(r#"
+            ($define! other (call/cc ($lambda (k) k)))
+            (call/cc ($lambda (top)
+                ($let ((g (guard-continuation () top (list (list other ($lambda (v divert) 0))))))
+                    (apply (continuation->applicative (extend-continuation g
+                        ($lambda () (apply (continuation->applicative top) 2)))) ()))))
+        "#).last().unwrap(), "2");
+    }
+
+    #[test]
+    fn eval_constants_and_chars() {
+        assert_eq!(eval_str("#true (boolean? #false) (inert? #inert) (ignore? #ignore) ($if #f 1 2)"),
+            vec!["#t", "#t", "#t", "#t", "2"]);
+        assert_eq!(eval_str("(char? #\\a) (char->integer #\\A) (integer->char 955) (equal? #\\a #\\x61)"),
+            vec!["#t", "65", "λ", "#t"]);
+    }
+
+    #[test]
+    fn term_written() {
+        let term = Term::list([Term::from("a\"b\n".to_string()), Term::from(1)]);
+        assert_eq!(term.written().to_string(), "(\"a\\\"b\\n\" 1)");
+        assert_eq!(term.to_string(), "(a\"b\n 1)");
+        let term = Term::list([Term::from('a'), Term::from('\n'), Term::from('\x01')]);
+        assert_eq!(term.written().to_string(), "(#\\a #\\newline #\\x1)");
+    }
+
+    #[test]
+    fn term_deep_nesting() {
+        let depth = 1_000_000;
+        let term = Term::list([Term::from(1), Term::vector(vec![Term::from(Symbol::new("a"))])]);
+        assert_eq!(format!("{term:?}"), concat!(
+            "Term { value: Pair(Pair { car: Term { value: Number(Integer(1)), span: None }, ",
+            "cdr: Term { value: Pair(Pair { car: Term { value: Vector([Term { value: Sym(Symbol(\"a\")), span: None }]), span: None }, ",
+            "cdr: Term { value: Nil, span: None } }), span: None } }), span: None }"
+        ));
+        let nested = (0..depth).fold(Term::new(), |term, i| match i % 2 {
+            0 => Term::list([term]),
+            _ => Term::vector(vec![term])
+        });
+        let text = nested.to_string();
+        assert_eq!(text.len(), 2 * depth + 2);
+        assert!(text.starts_with("[([(") && text.ends_with(")])]"));
+        assert!(format!("{nested:?}").starts_with("Term { value: Vector([Term { value: Pair(Pair { car: "));
+        let same = (0..depth).fold(Term::new(), |term, i| match i % 2 {
+            0 => Term::list([term]),
+            _ => Term::vector(vec![term])
+        });
+        assert_eq!(nested, same);
+        assert_ne!(nested, Term::list([same]));
+        drop(nested);
+        // A long list is dropped along its tail.
+        let long = Term::list((0..depth as i64).map(Term::from));
+        assert_eq!(long.to_list().unwrap().len(), depth);
+    }
+
+    #[test]
+    fn term_cyclic_debug() {
+        let list = Term::list([Term::from(1), Term::from(2)]);
+        let pair = list.as_pair().unwrap().clone();
+        pair.cdr().as_pair().unwrap().set_cdr(list.clone());
+        pair.set_car(list.clone());
+        assert_eq!(format!("{list:?}"), concat!(
+            "Term { value: Pair(Pair { car: ..., ",
+            "cdr: Term { value: Pair(Pair { car: Term { value: Number(Integer(2)), span: None }, cdr: ... }), span: None } }), ",
+            "span: None }"
+        ));
+        assert!(format!("{pair:?}").starts_with("Pair { car: ..., cdr: Term { value: Pair(Pair { car: Term"));
+    }
+
+    #[test]
+    fn eval_deep_nesting() {
+        let depth = 100_000;
+        let source = format!("($quote {}x{}) {}1{}", "([".repeat(depth), "])".repeat(depth), "{".repeat(depth), "}".repeat(depth));
+        let terms = eval_source("test", &source, 1_000).unwrap();
+        assert_eq!(terms[0].to_string().len(), 4 * depth + 1);
+        assert_eq!(terms[1].to_string(), "1");
+    }
+}
@@ -0,0 +1,9 @@
+pub mod command;
+pub mod error;
+mod macros;
+pub mod parser;
+pub mod syntax;
+pub mod cst;
+pub mod format;
+pub mod evaluation;
+pub mod interpreter;
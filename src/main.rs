@@ -1,15 +1,13 @@
-mod command;
-mod error;
-mod macros;
-mod parser;
-mod syntax;
-mod evaluation;
-mod interpreter;
+use thesis_interpreter::*;
 
 fn main() {
     use command::*;
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).is_some_and(|arg| arg == "fmt") {
+        return run_fmt(&args[2..])
+    }
     let mut app = Command::new("thesis", 
-r#"The prototype of Thesis interpreter."#);
+r#"The prototype of Thesis interpreter. Run 'thesis fmt --help' for the formatter."#);
     app.add_arg(
         Arg::new("--help")
             .short_id('h')
@@ -31,64 +29,130 @@ r#"The prototype of Thesis interpreter."#);
             .parameterize(Parameter::Optional("\"ast\""))
             .description("Specify the output target.")
             .details(
-r#"The supported output targets are listed here. Note that only a work in progress target is support currently.
-      - "ast": Output as a desugared abstract syntax tree (in list form)."#)
+r#"The supported output targets are listed here.
+      - "ast": Output the syntax tree as source, one top-level form per line, which reads back into the same tree.
+      - "tokens": Output the token stream, one token per line with its range and kind."#)
     );
     app.add_arg(
         Arg::new("script")
             .parameterize(Parameter::Optional("-")));
-    let args: Vec<String> = std::env::args().into_iter().collect();
     let map = match app.match_with(args[1..].to_vec()) {
         Ok(map) => map,
         Err(err) => seq!(println!("{}", err), return)
     };
-    
+    if let Some(target) = map.get("target").filter(|target| !matches!(target.as_str(), "ast" | "tokens")) {
+        return println!("Error: Unknown output target '{target}'.")
+    }
+
     for (key, val) in &map {
         match key.as_str() {
             "help" => seq!(app.print_help(), break),
             "version" => seq!(println!(env!("CARGO_PKG_VERSION")), break),
-            // In the future, the implementation will only
-            // evaluate the script without specifying '--output'.
             "script" => {
-                if map.get("script").unwrap() == "-" {
+                if val == "-" {
                     run_loop()
-                } else {
-                    execute_script(val, map.get("output")).unwrap()
+                } else if let Err(err) = execute_script(val, map.get("output"), map.get("target")) {
+                    println!("Error: Failed to process '{val}': {err}");
+                    std::process::exit(1)
                 }
             },
-            "target" => match map.get("target").unwrap().as_str() {
-                "ast" => continue,
-                _ => panic!()
-            },
             _ => {}
         }
     }
 }
 
+fn run_fmt(args: &[String]) {
+    use command::*;
+    use format::*;
+    use parser::*;
+    let mut app = Command::new("thesis fmt",
+r#"Format the source files in place."#);
+    app.add_arg(
+        Arg::new("--help")
+            .short_id('h')
+            .description("Print the help message.")
+            .interrupt());
+    app.add_arg(
+        Arg::new("--check")
+            .description("Exit with 1 if any file is not formatted, instead of formatting them."));
+    app.add_arg(
+        Arg::new("--width")
+            .short_id('w')
+            .parameterize(Parameter::Required)
+            .description("Specify the maximum width of the lines, which is 80 by default."));
+    app.add_arg(Arg::new("paths").variadic());
+    let map = match app.match_with(args.to_vec()) {
+        Ok(map) => map,
+        Err(err) => seq!(println!("{}", err), std::process::exit(2))
+    };
+    if map.contains_key("help") { return app.print_help() }
+    let mut formatter = SourceFormatter::default();
+    if let Some(width) = map.get("width") {
+        match width.parse() {
+            Ok(width) => formatter = formatter.with_width(width),
+            Err(_) => seq!(println!("Error: Invalid width '{width}'."), std::process::exit(2))
+        }
+    }
+    // Every file is processed, the exit code tells the worst result.
+    let (mut unformatted, mut failed) = (false, false);
+    for path in map["paths"].split('\0') {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => seq!(println!("Error: Failed to read '{path}': {err}"), failed = true, continue)
+        };
+        let src = SrcInfo::new(path, &content);
+        let formatted = match formatter.format(&src) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                for err in errors { err.print(&src) }
+                seq!(failed = true, continue)
+            }
+        };
+        if formatted == content { continue }
+        if map.contains_key("check") {
+            seq!(println!("'{path}' is not formatted."), unformatted = true, continue)
+        }
+        if let Err(err) = std::fs::write(path, formatted) {
+            seq!(println!("Error: Failed to write '{path}': {err}"), failed = true)
+        }
+    }
+    if failed { std::process::exit(2) }
+    if unformatted { std::process::exit(1) }
+}
+
 fn run_loop() -> ! {
     use interpreter::*;
     let mut instance = Interpreter::new();
     instance.run_interactive()
 }
 
-fn execute_script(path: &String, out: Option<&String>) -> Result<(), std::io::Error> {
+fn execute_script(path: &String, out: Option<&String>, target: Option<&String>) -> Result<(), std::io::Error> {
     use std::fs::*;
     use std::io::Write;
+    use interpreter::*;
     use parser::*;
-    let input = std::fs::read(path);
-    let content = String::from_utf8(match input {
-        Ok(val) => val,
-        Err(err) => return Err(err),
-    }).unwrap_or_else(|err| {
-        panic!("{err}");
-    });
-    let mut parser = SyntacticParser::new(share!(SrcInfo::new(path, &content)));
-        parser.parse();
-    match out {
-        Some(out_path) => {
-            let mut file = File::create(out_path)?;
-            return write!(file, "{}", parser.tree())
+    let content = String::from_utf8(std::fs::read(path)?)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    // The script is evaluated only if neither a target nor an output file
+    // is given, the syntax tree is written by default otherwise.
+    let dump = match (target.map(String::as_str), out) {
+        (Some("tokens"), _) => {
+            let mut lexer = LexicalParser::new();
+            lexer.parse_str(&content);
+            lexer.results().iter().map(|token| {
+                let (start, end) = (token.start(), token.end());
+                format!("{}:{}-{}:{} {:?} {:?}\n", start.ln(), start.col(), end.ln(), end.col(), token.kind(), token.text())
+            }).collect::<String>()
         },
-        None => Ok(())
+        (Some(_), _) | (None, Some(_)) => {
+            let mut parser = SyntacticParser::new(share!(SrcInfo::new(path, &content)));
+            parser.parse();
+            parser.tree().to_source()
+        },
+        (None, None) => return seq!(Interpreter::new().run_script(path, content), Ok(()))
+    };
+    match out {
+        Some(out_path) => write!(File::create(out_path)?, "{dump}"),
+        None => seq!(print!("{dump}"), Ok(()))
     }
 }
@@ -1,12 +1,15 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::ops::Range;
 use std::process::exit;
 use std::rc::Rc;
-use ariadne::{Color, Fmt, Label, Source};
+use ariadne::{Color, Fmt, Label};
+use unicode_width::UnicodeWidthChar;
 
 use crate::error::{Error, ErrorKind};
 use crate::{if_or, seq};
-use crate::syntax::{Node, Symbol};
+use crate::syntax::{parse_char, Node, NodeValue, NumberLiteral, Symbol};
 
 #[derive(Debug)]
 pub struct SrcInfo {
@@ -20,137 +23,384 @@ impl SrcInfo {
     }
 }
 
+/// The kind of a bracket pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    /// `( )`
+    Paren,
+    /// `[ ]`
+    Bracket,
+    /// `{ }`
+    Brace
+}
+
+impl Delimiter {
+    pub fn open(&self) -> char {
+        match self {
+            Self::Paren => '(',
+            Self::Bracket => '[',
+            Self::Brace => '{'
+        }
+    }
+
+    pub fn close(&self) -> char {
+        match self {
+            Self::Paren => ')',
+            Self::Bracket => ']',
+            Self::Brace => '}'
+        }
+    }
+}
+
+/// The abbreviations of quotations written before a datum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotePrefix {
+    /// `'`
+    Quote,
+    /// `` ` ``
+    Quasiquote,
+    /// `,`
+    Unquote,
+    /// `,@`
+    UnquoteSplicing
+}
+
+impl QuotePrefix {
+    pub fn text(&self) -> &'static str {
+        match self {
+            Self::Quote => "'",
+            Self::Quasiquote => "`",
+            Self::Unquote => ",",
+            Self::UnquoteSplicing => ",@"
+        }
+    }
+
+    /// The symbol of the list the abbreviation stands for.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Quote => "$quote",
+            Self::Quasiquote => "$quasiquote",
+            Self::Unquote => "unquote",
+            Self::UnquoteSplicing => "unquote-splicing"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Open(Delimiter),
+    Close(Delimiter),
+    /// A string literal along with its quotes, whose escapes are kept.
+    String,
+    Number,
+    Symbol,
+    /// A character literal starting with `#\`.
+    Char,
+    Boolean,
+    /// Any other literal starting with `#`, such as `#inert` and `#ignore`.
+    Constant,
+    Quote(QuotePrefix),
+    /// A single `.` followed by the tail of a dotted list.
+    Dot,
+    /// `#;`, which comments out the next datum.
+    DatumComment,
+    /// A line comment or a block comment.
+    Comment,
+    Whitespace
+}
+
+impl TokenKind {
+    /// Classify a token made of the characters between the delimiters.
+    pub fn of_atom(text: &str) -> Self {
+        match text {
+            "." => Self::Dot,
+            "#t" | "#true" | "#f" | "#false" => Self::Boolean,
+            _ if text.starts_with("#\\") => Self::Char,
+            _ if NumberLiteral::is_numeric(text) => Self::Number,
+            _ if text.starts_with('#') => Self::Constant,
+            _ => Self::Symbol
+        }
+    }
+
+    /// Whether the token is a comment or whitespaces, which are ignored
+    /// by the syntactic parser.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, Self::Comment | Self::Whitespace)
+    }
+}
+
+/// A token of the source along with its kind and where it starts and ends.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Token(String);
+pub struct Token {
+    kind: TokenKind,
+    text: String,
+    start: SourcePos,
+    end: SourcePos
+}
+
+impl Token {
+    /// Construct a token starting at the position, whose end is located by
+    /// its text.
+    pub fn new(kind: TokenKind, text: String, start: SourcePos) -> Self {
+        let mut end = start;
+        for ch in text.chars() { end.advance(ch) }
+        Self { kind, text, start, end }
+    }
+
+    pub fn kind(&self) -> TokenKind { self.kind }
+
+    pub fn text(&self) -> &str { &self.text }
+
+    pub fn start(&self) -> SourcePos { self.start }
+
+    pub fn end(&self) -> SourcePos { self.end }
+
+    /// The byte range of the token.
+    pub fn range(&self) -> Range<usize> { self.start.byte()..self.end.byte() }
+}
 
 impl AsRef<str> for Token {
-    fn as_ref(&self) -> &str { self.0.as_str() }
+    fn as_ref(&self) -> &str { self.text.as_str() }
 }
 
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", self.text)
     }
 }
 
+/// An atom at the start of the source.
 impl From<&str> for Token {
     fn from(value: &str) -> Self {
-        Self(value.to_string())
+        Self::new(TokenKind::of_atom(value), value.to_string(), SourcePos::new())
     }
 }
 
-impl From<String> for Token {
-    fn from(value: String) -> Self { Self(value) }
+impl From<Token> for String {
+    fn from(value: Token) -> Self { value.text }
 }
 
-impl Into<String> for Token {
-    fn into(self) -> String { self.0 }
+/// A position in the source, whose line and columns are counted from 1
+/// and byte offset from 0. The column is counted by characters, and the
+/// display column by the width of the characters in a terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SourcePos {
+    byte: usize,
+    ln: usize,
+    col: usize,
+    display_col: usize
 }
 
-impl Token {
-    pub fn as_left_parentheses(&self) -> &str {
-        match self.0.as_str() {
-            ")" => "(",
-            "]" => "[",
-            "}" => "{",
-            _ => panic!("Error: Not a parentheses.")
-        }
+impl SourcePos {
+    /// The start of a source.
+    pub fn new() -> Self {
+        Self { byte: 0, ln: 1, col: 1, display_col: 1 }
     }
 
-    pub fn as_right_parentheses(&self) -> &str {
-        match self.0.as_str() {
-            "(" => ")",
-            "[" => "]",
-            "{" => "}",
-            _ => panic!("Error: Not a parentheses.")
+    /// Locate the byte offset in the text, an offset inside a character
+    /// is moved back to the start of the character.
+    pub fn locate(text: &str, byte: usize) -> Self {
+        let mut pos = Self::new();
+        for ch in text.chars() {
+            if pos.byte + ch.len_utf8() > byte { break }
+            pos.advance(ch);
         }
+        pos
+    }
+
+    /// Convert a range of characters in the text to a range of bytes.
+    pub fn byte_range(text: &str, chars: Range<usize>) -> Range<usize> {
+        let byte = |i: usize| text.char_indices().nth(i).map_or(text.len(), |(byte, _)| byte);
+        byte(chars.start)..byte(chars.end)
     }
 
-    pub fn match_left_parentheses(&self, other: &String) -> bool {
-        match self.0.as_str() {
-            ")" | "]" | "}" => if_or!(other == self.as_left_parentheses(), true, false),
-            _ => panic!("Error: Not a parentheses.")
+    pub fn ln(&self) -> usize { self.ln }
+
+    pub fn col(&self) -> usize { self.col }
+
+    pub fn display_col(&self) -> usize { self.display_col }
+
+    pub fn byte(&self) -> usize { self.byte }
+
+    /// Move over the character at the position.
+    pub fn advance(&mut self, ch: char) {
+        self.byte += ch.len_utf8();
+        if ch == '\n' {
+            seq!(self.ln += 1, self.col = 1, self.display_col = 1)
+        } else {
+            seq!(self.col += 1, self.display_col += ch.width().unwrap_or(0))
         }
     }
 }
 
-/// Representing the current position as (line, column, index) of a source parser.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub struct SourcePos(usize, usize, usize);
+impl Default for SourcePos {
+    fn default() -> Self { Self::new() }
+}
 
-impl SourcePos {
-    pub fn ln(&self) -> usize { self.0 }
+/// The byte range of a token or a node in the source identified by `id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    id: Rc<str>,
+    range: Range<usize>
+}
 
-    pub fn col(&self) -> usize { self.1 }
+impl Span {
+    pub fn new(id: Rc<str>, range: Range<usize>) -> Self {
+        Self { id, range }
+    }
 
-    pub fn i(&self) -> usize { self.2 }
+    pub fn id(&self) -> &str { &self.id }
 
-    pub fn next_ln(&mut self) { seq!(self.0 += 1, self.1 += 1, self.2 += 1) }
+    pub fn range(&self) -> Range<usize> { self.range.clone() }
 
-    pub fn next_col(&mut self) { seq!(self.1 += 1, self.2 += 1) }
-}
+    pub fn start(&self) -> usize { self.range.start }
 
-impl From<(usize, usize, usize)> for SourcePos {
-    fn from(value: (usize, usize, usize)) -> Self { Self(value.0, value.1, value.2) }
+    pub fn end(&self) -> usize { self.range.end }
 }
 
 #[derive(Debug)]
 pub struct LexicalParser {
     buf: String,
     pos: SourcePos,
-    results: Vec<(SourcePos, Token)>,
+    /// Position of the first character in the buffer.
+    start: SourcePos,
+    results: Vec<Token>,
     // 0 indicates initial state
     // 1 indicates parsing string literal
     // 2 indicates to unescape characters
-    parsing_context: usize
+    // 3 indicates skipping a line comment
+    // 4 indicates skipping a block comment
+    // 5 indicates collecting whitespaces
+    parsing_context: usize,
+    /// Nesting depth of the block comments and the previous character in them.
+    comment: (usize, char),
+    /// Position where the pending string literal or block comment is opened.
+    opening: SourcePos
+}
+
+impl Default for LexicalParser {
+    fn default() -> Self { Self::new() }
 }
 
 impl LexicalParser {
     pub fn new() -> Self {
-        Self { buf: "".to_string(), pos: (1, 1, 1).into(), results: vec![], parsing_context: 0 }
+        Self {
+            buf: "".to_string(), pos: SourcePos::new(), start: SourcePos::new(), results: vec![], parsing_context: 0,
+            comment: (0, '\0'), opening: SourcePos::new()
+        }
     }
 
-    pub fn results(self) -> Vec<(SourcePos, Token)> {
+    /// All the tokens including the comments and whitespaces.
+    pub fn results(self) -> Vec<Token> {
         self.results
     }
 
+    /// The tokens except the comments and whitespaces.
     pub fn tokens(&self) -> Vec<Token> {
-        self.results.iter().map(|pair| pair.1.clone()).collect()
+        self.results.iter().filter(|token| !token.kind.is_trivia()).cloned().collect()
+    }
+
+    /// Position of the block comment that is never closed.
+    pub fn unterminated_comment(&self) -> Option<SourcePos> {
+        if_or!(self.parsing_context == 4, Some(self.opening), None)
+    }
+
+    /// Position of the opening quote of the string literal that is never closed.
+    pub fn unterminated_string(&self) -> Option<SourcePos> {
+        if_or!(matches!(self.parsing_context, 1 | 2), Some(self.opening), None)
     }
 
     pub fn parse_c(&mut self, ch: char) {
+        if self.parsing_context == 5 && !Self::is_whitespace(ch) {
+            self.push_buf_as_token(TokenKind::Whitespace);
+            self.parsing_context = 0;
+        }
+        if self.buf.is_empty() { self.start = self.pos }
         match ch {
+            ch if self.parsing_context == 5 => self.buf.push(ch),
+            '\n' if self.parsing_context == 3 => {
+                self.push_buf_as_token(TokenKind::Comment);
+                seq!(self.start = self.pos, self.buf.push(ch), self.parsing_context = 5)
+            },
+            ch if self.parsing_context == 3 => self.buf.push(ch),
+            ch if self.parsing_context == 4 => {
+                self.buf.push(ch);
+                self.skip_block_comment(ch);
+                if self.parsing_context == 0 { self.push_buf_as_token(TokenKind::Comment) }
+            },
+            ch if self.parsing_context == 2 => {
+                self.buf.push(ch);
+                self.parsing_context = 1;
+            },
             ch if self.parsing_context == 1 => {
                 self.buf.push(ch);
                 if ch == '\\' {
                     self.parsing_context = 2;
-                }
-
-                if ch == '"' || ch == '\'' {
+                } else if ch == '"' {
+                    // The escapes are left to the syntactic parser.
+                    self.push_buf_as_token(TokenKind::String);
                     self.parsing_context = 0;
                 }
             },
-            '(' | '[' | '{' => {
-                self.push_token(String::from(ch).into());
-            }
-            ')' | ']' | '}'=> {
+            // The character after `#\` is always a part of the literal.
+            ch if self.buf == "#\\" => self.buf.push(ch),
+            '(' | '[' | '{' | ')' | ']' | '}' => {
                 self.try_collect_buf();
-                self.push_token(String::from(ch).into())
+                let kind = match ch {
+                    '(' => TokenKind::Open(Delimiter::Paren),
+                    '[' => TokenKind::Open(Delimiter::Bracket),
+                    '{' => TokenKind::Open(Delimiter::Brace),
+                    ')' => TokenKind::Close(Delimiter::Paren),
+                    ']' => TokenKind::Close(Delimiter::Bracket),
+                    _ => TokenKind::Close(Delimiter::Brace)
+                };
+                self.push_token(kind, ch)
             }
-            ',' | ';' => self.push_token(String::from(ch).into()),
-            '\'' | '"'=> {
+            // `#;` comments out the next datum, which is left to the syntactic parser.
+            ';' if self.buf == "#" => {
                 self.buf.push(ch);
-                if self.parsing_context == 0 {
-                    self.parsing_context = 1;
-                } else if self.parsing_context == 2 {
-                    self.parsing_context = 1;
-                }
+                self.push_buf_as_token(TokenKind::DatumComment)
+            },
+            ';' => {
+                self.try_collect_buf();
+                seq!(self.start = self.pos, self.buf.push(ch), self.parsing_context = 3)
+            },
+            '|' if self.buf == "#" => {
+                self.buf.push(ch);
+                self.opening = self.start;
+                self.comment = (1, '\0');
+                self.parsing_context = 4;
+            },
+            '\'' | '`' | ',' => {
+                self.try_collect_buf();
+                let prefix = match ch {
+                    '\'' => QuotePrefix::Quote,
+                    '`' => QuotePrefix::Quasiquote,
+                    _ => QuotePrefix::Unquote
+                };
+                self.push_token(TokenKind::Quote(prefix), ch)
+            },
+            // `,@` is a single token.
+            '@' if self.buf.is_empty() && self.results.last()
+                .is_some_and(|last| last.kind == TokenKind::Quote(QuotePrefix::Unquote) && last.end == self.pos) => {
+                let last = self.results.pop().unwrap();
+                self.results.push(Token::new(TokenKind::Quote(QuotePrefix::UnquoteSplicing), ",@".to_string(), last.start))
+            },
+            '"'=> {
+                self.try_collect_buf();
+                self.start = self.pos;
+                self.buf.push(ch);
+                self.opening = self.pos;
+                self.parsing_context = 1;
+            },
+            ch if Self::is_whitespace(ch) => {
+                self.try_collect_buf();
+                seq!(self.start = self.pos, self.buf.push(ch), self.parsing_context = 5)
             },
-            ch if ch.is_ascii_whitespace() || ch == '\x0B' => self.try_collect_buf(),
             ch => self.buf.push(ch)
         }
 
-        if ch != '\n' { self.pos.next_col() } else { self.pos.next_ln() }
+        self.pos.advance(ch);
     }
 
     pub fn parse_str(&mut self, source: &str) {
@@ -158,20 +408,101 @@ impl LexicalParser {
         self.try_collect_buf();
     }
 
+    fn is_whitespace(ch: char) -> bool {
+        ch.is_ascii_whitespace() || ch == '\x0B'
+    }
+
+    /// Skip a character of a block comment, in which `#|` and `|#` are
+    /// nested.
+    fn skip_block_comment(&mut self, ch: char) {
+        let (depth, prev) = self.comment;
+        self.comment = match (prev, ch) {
+            ('#', '|') => (depth + 1, '\0'),
+            ('|', '#') if depth == 1 => seq!(self.parsing_context = 0, (0, '\0')),
+            ('|', '#') => (depth - 1, '\0'),
+            (_, ch) => (depth, ch)
+        };
+    }
+
+    /// Push a token of the current character.
     #[inline]
-    fn push_token(&mut self, token: Token) {
-        self.results.push((self.pos, token))
+    fn push_token(&mut self, kind: TokenKind, ch: char) {
+        self.results.push(Token::new(kind, ch.to_string(), self.pos))
     }
 
     #[inline]
-    fn push_buf_as_token(&mut self) {
-        self.results.push((self.pos, core::mem::take(&mut self.buf).into()))
+    fn push_buf_as_token(&mut self, kind: TokenKind) {
+        self.results.push(Token::new(kind, core::mem::take(&mut self.buf), self.start))
     }
 
-    /// Try to collect the buffer
+    /// Try to collect the buffer, whose kind is decided by the state.
     #[inline]
     fn try_collect_buf(&mut self) {
-        if !self.buf.is_empty() { self.push_buf_as_token() }
+        if self.buf.is_empty() { return }
+        let kind = match self.parsing_context {
+            1 | 2 => TokenKind::String,
+            3 | 4 => TokenKind::Comment,
+            5 => TokenKind::Whitespace,
+            _ => TokenKind::of_atom(&self.buf)
+        };
+        self.push_buf_as_token(kind)
+    }
+}
+
+/// The tree being read, with an explicit stack of the lists still open,
+/// so that every token is added to the innermost one in constant time.
+struct TreeBuilder {
+    root: Node,
+    /// The open lists from the outermost to the innermost one, along with
+    /// their opening delimiters or quote prefixes and where they are.
+    open: Vec<((SourcePos, TokenKind), Node)>,
+}
+
+impl TreeBuilder {
+    fn new(root: Node) -> Self {
+        Self { root, open: vec![] }
+    }
+
+    fn depth(&self) -> usize { self.open.len() }
+
+    /// The opening delimiter or quote prefix of the innermost list.
+    fn last(&self) -> Option<&(SourcePos, TokenKind)> {
+        self.open.last().map(|(opening, _)| opening)
+    }
+
+    /// The innermost list being read, or the root at the top level.
+    fn current(&mut self) -> &mut Node {
+        match self.open.last_mut() {
+            Some((_, node)) => node,
+            None => &mut self.root
+        }
+    }
+
+    fn open(&mut self, opening: (SourcePos, TokenKind), node: Node) {
+        self.open.push((opening, node))
+    }
+
+    /// Move the innermost list closed at `end` into the enclosing one, a
+    /// list with its dotted tail read takes the last node as the tail.
+    fn close(&mut self, end: usize, dotted: bool) {
+        let Some((_, mut node)) = self.open.pop() else { return };
+        if let (true, NodeValue::List(items)) = (dotted, &mut node.value) {
+            if let Some(tail) = items.pop() {
+                node.value = NodeValue::DottedList(core::mem::take(items), Box::new(tail));
+            }
+        }
+        if let Some(opening) = node.span() {
+            node.set_span(Span::new(opening.id.clone(), opening.start()..end));
+        }
+        self.current().push(node);
+    }
+
+    /// Close all the open lists at `end` and take the tree.
+    fn finish(mut self, end: usize) -> Node {
+        while !self.open.is_empty() {
+            self.close(end, false)
+        }
+        self.root
     }
 }
 
@@ -182,166 +513,432 @@ pub struct SyntacticParser {
 
 impl SyntacticParser {
     pub fn new(src: Rc<RefCell<SrcInfo>>) -> Self {
-        Self { src, tree: Node::List(vec![]) }
-    }
-
-    fn first_quoted(s: &str) -> bool {
-        match s.chars().nth(0).unwrap() {
-            '\'' | '"' => true,
-            _ => false
-        }
+        Self { src, tree: NodeValue::List(vec![]).into() }
     }
 
     pub fn parse(&mut self) {
-        let _ = self.try_parse().is_err_and(|err| {
-            let _ = err.report
-                .unwrap()
-                .finish()
-                .print((self.src.borrow().id.clone(), Source::from(&self.src.borrow().text)))
-                .unwrap();
+        if let Err(errors) = self.try_parse() {
+            for err in errors {
+                err.print(&self.src.borrow());
+            }
             exit(1);
-        });
+        }
     }
 
-    pub fn try_parse(&mut self) -> Result<(), Error> {
-        let mut nest: (i32, Vec<(SourcePos, String)>) = (0, vec![]); // (Nesting Depth, Parentheses Kind)
-        let mut current = &mut self.tree;
-
+    /// Read the source into the tree. The parser recovers from the syntax
+    /// errors to report all of them, invalid tokens are kept as symbols,
+    /// unmatched closing delimiters are skipped and missing ones are
+    /// inserted.
+    pub fn try_parse(&mut self) -> Result<(), Vec<Error>> {
         let src = self.src.borrow();
+        let mut lexer = LexicalParser::new();
+        lexer.parse_str(&src.text);
+        let (tree, errors) = Self::read_lexed(&src, &lexer);
+        drop(src);
+        self.tree = tree;
+        if_or!(errors.is_empty(), Ok(()), Err(errors))
+    }
 
-        let tokens = {
-            let mut lexer = LexicalParser::new();
-            lexer.parse_str(&src.text);
-            lexer.results()
-        };
+    /// Read the tokens of the source from the lexer into the tree, along
+    /// with the syntax errors reported by [`Self::try_parse`].
+    pub(crate) fn read_lexed(src: &SrcInfo, lexer: &LexicalParser) -> (Node, Vec<Error>) {
+        let mut errors = vec![];
+        // Nesting depths of the pending datum comments and where they are.
+        let mut commented: Vec<(usize, SourcePos)> = vec![];
+        // Nesting depths of the dotted lists being read, where their dots
+        // are and whether their tails are read.
+        let mut dotted: Vec<(usize, SourcePos, bool)> = vec![];
+        let id: Rc<str> = src.id.as_str().into();
+        let mut nest = TreeBuilder::new(
+            Node::from(NodeValue::List(vec![])).with_span(Span::new(id.clone(), 0..src.text.len())));
 
-        for (pos, token) in tokens {
-            match token.0.as_str() {
-                "(" | "[" | "{" => {
-                    nest.0 += 1;
-                    nest.1.push((pos, token.0.to_string()));
-                    current = current.push(Node::List(vec![]));
+        // Where the string literal never closed starts.
+        let mut unterminated = None;
+        if let Some(pos) = lexer.unterminated_string() {
+            unterminated = Some(pos.byte());
+            errors.push(Error::new(ErrorKind::InvalidSyntax)
+                .with_message("The string literal is never closed.".to_string())
+                .with_span(pos.byte()..(pos.byte() + 1))
+                .return_error(src, pos, "String literal opened here.".to_string()))
+        }
+        if let Some(pos) = lexer.unterminated_comment() {
+            errors.push(Error::new(ErrorKind::InvalidSyntax)
+                .with_message("The block comment is never closed.".to_string())
+                .with_span(pos.byte()..(pos.byte() + 2))
+                .return_error(src, pos, "Block comment opened here.".to_string()))
+        }
+        let tokens = lexer.tokens();
+        // A delimiter opened at the start of a line begins a new top-level
+        // form, if some delimiters are never closed.
+        let unbalanced = tokens.iter().map(|token| match token.kind() {
+            TokenKind::Open(_) => 1,
+            TokenKind::Close(_) => -1,
+            _ => 0
+        }).sum::<i32>() > 0;
+        // Where the last token ends.
+        let mut end = 0;
+
+        for token in tokens {
+            // Whether a datum is completed by the token.
+            let mut completed = true;
+            let (pos, range, text) = (token.start(), token.range(), token.text());
+            let span = Span::new(id.clone(), range.clone());
+            let located = |value: NodeValue| Node::from(value).with_span(span.clone());
+            if unbalanced && nest.depth() > 0 && pos.col() == 1 && matches!(token.kind(), TokenKind::Open(_)) {
+                errors.push(Self::unclosed(src, nest.last().unwrap()));
+                while nest.depth() > 0 {
+                    Self::close(&mut nest, &mut commented, &mut dotted, end);
+                }
+            }
+            end = range.end;
+            let depth = nest.depth();
+            match token.kind() {
+                TokenKind::Open(delimiter) => {
+                    // The span is extended to the closing delimiter.
+                    nest.open((pos, token.kind()), Node::delimited(delimiter, vec![]).with_span(span.clone()));
+                    completed = false;
+                }
+                TokenKind::Quote(prefix) => {
+                    // The list is closed along with the next datum.
+                    let symbol = located(NodeValue::Symbol(prefix.name().into()));
+                    nest.open((pos, token.kind()), located(NodeValue::List(vec![symbol])));
+                    completed = false;
+                }
+                TokenKind::DatumComment => {
+                    commented.push((depth, pos));
+                    completed = false;
+                }
+                TokenKind::Dot => {
+                    if let Some(&(_, pos)) = commented.last().filter(|(level, _)| *level == depth) {
+                        errors.push(Self::missing_commented_datum(src, pos));
+                        commented.pop();
+                    }
+                    let empty = nest.current().as_ref().is_empty();
+                    let message = match nest.last() {
+                        Some((_, TokenKind::Open(Delimiter::Paren))) if dotted.last().is_some_and(|dot| dot.0 == depth) =>
+                            "Only one '.' can be placed in a list.",
+                        Some((_, TokenKind::Open(Delimiter::Paren))) if empty =>
+                            "Expected a datum before '.' in the list.",
+                        Some((_, TokenKind::Open(Delimiter::Paren))) => seq!(dotted.push((depth, pos, false)), continue),
+                        Some((_, TokenKind::Open(_))) => "'.' can only be placed in a parenthesized list.",
+                        _ => "Unexpected '.' outside a list."
+                    };
+                    errors.push(Self::invalid_token(src, pos, text, message.to_string(), "Misplaced"));
+                    continue
                 }
-                ")" | "]" | "}" => {
-                    nest.0 -= 1;
-                    let wrapped_last = nest.1.last();
-                    let last: &(SourcePos, String);
-                    match wrapped_last {
-                        Some(val) => last = val,
-                        None => return Err(Error::new(ErrorKind::InvalidSyntax)
-                        .with_message(
-                            format!("No corresponding '{}' can be found for '{token}'.",
-                            token.as_left_parentheses()))
-                        .with_span((pos.i()-1)..pos.i())
-                        .return_error(&src, pos, format!("Invalid '{token}' here.")))
+                TokenKind::Close(delimiter) => {
+                    if let Some(&(_, pos)) = commented.last().filter(|(level, _)| *level == depth) {
+                        errors.push(Self::missing_commented_datum(src, pos));
+                    }
+                    while let Some(last) = nest.last().filter(|(_, kind)| matches!(kind, TokenKind::Quote(_))) {
+                        errors.push(Self::missing_quoted_datum(src, last));
+                        Self::close(&mut nest, &mut commented, &mut dotted, range.start);
                     }
-                    if !token.match_left_parentheses(&last.1) {
-                        use Color::*;
-                        return Err(Error::new(ErrorKind::InvalidSyntax)
+                    let matched = nest.open.iter().rposition(|((_, kind), _)| *kind == TokenKind::Open(delimiter));
+                    match nest.last() {
+                        None => errors.push(Error::new(ErrorKind::InvalidSyntax)
                             .with_message(
-                        format!(
-                    "'{}' is required, but only to found '{token}'", Token(last.1.clone()).as_right_parentheses()
-                                )
-                            )
-                            .with_span(pos.i()-1..pos.i())
-                            .with_label(
-                                Label::new((src.id.clone(), (last.0.2-1)..last.0.2))
-                                    .with_color(Fixed(86))
-                                    .with_message(
-                                        format!("Opening delimiter '{}{}", 
-                                            last.1.clone().fg(Red), "' occurred here.".fg(Cyan)).fg(Cyan))
-                                    .with_order(1)
-                            )
-                            .return_error(&src, pos,
-                            format!("Invalid closing '{}{}.", token.fg(Fixed(81)), "' here".fg(Red)).fg(Red).to_string()))
+                                format!("No corresponding '{}' can be found for '{token}'.", delimiter.open()))
+                            .with_span(range.clone())
+                            .return_error(src, pos, format!("Invalid '{token}' here."))),
+                        Some(last) if matched != Some(nest.depth() - 1) =>
+                            errors.push(Self::mismatched(src, last, &token)),
+                        _ => ()
                     }
-                    nest.1.pop();
-                    current = &mut self.tree;
-                    for _ in 0..nest.0 {
-                        if let Node::List(ref mut list) = current {
-                            current = list.last_mut().unwrap();
-                        }
+                    // The closing delimiter is skipped if no list can be closed
+                    // by it, otherwise the missing ones are inserted before it.
+                    let Some(matched) = matched else { continue };
+                    while nest.depth() > matched + 1 {
+                        Self::close(&mut nest, &mut commented, &mut dotted, range.start);
                     }
+                    if let Some(&(_, dot, false)) = dotted.last().filter(|dot| dot.0 == nest.depth()) {
+                        errors.push(Self::invalid_token(src, dot, ".",
+                            "Expected a datum after '.' in the list.".to_string(), "Misplaced"));
+                    }
+                    Self::close(&mut nest, &mut commented, &mut dotted, range.end);
                 },
-                s if Self::first_quoted(s) => {
-                    match Self::try_unquote(s) {
-                        Ok(unquoted) => current.push(Node::String(unquoted)),
-                        Err(err) => return Err(err)
-                    };
-                },
-                n if n.chars().nth(0).unwrap().is_digit(10) => {
-                    for ch in n.chars() {
-                        if !ch.is_digit(10) {
-                            return Err(Error::new(ErrorKind::InvalidSyntax))
-                        }
+                TokenKind::String if unterminated == Some(pos.byte()) => continue,
+                TokenKind::String | TokenKind::Boolean | TokenKind::Char | TokenKind::Number | TokenKind::Constant
+                    | TokenKind::Symbol => {
+                    let (value, error) = Self::read_atom(src, &token);
+                    errors.extend(error);
+                    nest.current().push(located(value));
+                }
+                TokenKind::Comment | TokenKind::Whitespace => continue
+            }
+
+            // A completed datum closes the pending abbreviations, unless
+            // it's commented out.
+            if !completed { continue }
+            loop {
+                let depth = nest.depth();
+                if commented.last().is_some_and(|(level, _)| *level == depth) {
+                    commented.pop();
+                    nest.current().pop();
+                    break
+                }
+                if let Some(dot) = dotted.last_mut().filter(|dot| dot.0 == depth) {
+                    if dot.2 {
+                        let datum = nest.current().as_ref().last().and_then(Node::span).map_or(range.clone(), Span::range);
+                        errors.push(Error::new(ErrorKind::InvalidSyntax)
+                            .with_message("Only one datum can follow '.' in the list.".to_string())
+                            .with_span(datum)
+                            .with_label(Label::new((src.id.clone(), dot.1.byte()..(dot.1.byte() + 1)))
+                                .with_color(Color::Cyan)
+                                .with_message("The dot is placed here."))
+                            .return_error(src, pos, "Unexpected datum here.".to_string()));
+                        // The extra datum is dropped.
+                        nest.current().pop();
+                        break
                     }
-                    current.push(Node::Number(token.0));
+                    dot.2 = true;
                 }
-                _ => {
-                    let symbol = Symbol::try_from(token.0);
-                    current.push(Node::Symbol(symbol.unwrap_or_else(|err| panic!("{err}"))));
+                if !nest.last().is_some_and(|(_, kind)| matches!(kind, TokenKind::Quote(_))) { break }
+                Self::close(&mut nest, &mut commented, &mut dotted, range.end);
+            }
+        }
+
+        if let Some(&(_, pos)) = commented.last() {
+            errors.push(Self::missing_commented_datum(src, pos));
+        }
+        if let Some(last) = nest.last() {
+            errors.push(Self::unclosed(src, last));
+            while nest.depth() > 0 {
+                Self::close(&mut nest, &mut commented, &mut dotted, end);
+            }
+        }
+        (nest.finish(end), errors)
+    }
+
+    /// Close the innermost list being read at `end`, a dotted list takes
+    /// the last node as its tail only if the tail is read.
+    fn close(
+        nest: &mut TreeBuilder,
+        commented: &mut Vec<(usize, SourcePos)>,
+        dotted: &mut Vec<(usize, SourcePos, bool)>,
+        end: usize
+    ) {
+        let depth = nest.depth();
+        commented.retain(|(level, _)| *level < depth);
+        let tail_read = dotted.last().is_some_and(|dot| dot.0 == depth) && dotted.pop().is_some_and(|dot| dot.2);
+        nest.close(end, tail_read);
+    }
+
+    /// Report the innermost list never closed, or the quote prefix never
+    /// followed by a datum.
+    fn unclosed(src: &SrcInfo, last: &(SourcePos, TokenKind)) -> Error {
+        let TokenKind::Open(opening) = last.1 else {
+            return Self::missing_quoted_datum(src, last)
+        };
+        Error::new(ErrorKind::InvalidSyntax)
+            .with_message(
+                format!("No corresponding '{}' for '{}' was found.", opening.close(), opening.open()))
+            .with_span(last.0.byte()..(last.0.byte() + 1))
+            .return_error(src, last.0,
+                format!("Single '{}' found here.", opening.open().fg(Color::Red)))
+    }
+
+    /// Report the closing delimiter not matching the innermost opening one.
+    fn mismatched(src: &SrcInfo, last: &(SourcePos, TokenKind), token: &Token) -> Error {
+        use Color::*;
+        let opening = if let TokenKind::Open(opening) = last.1 { opening } else { Delimiter::Paren };
+        Error::new(ErrorKind::InvalidSyntax)
+            .with_message(
+        format!(
+    "'{}' is required, but only to found '{token}'", opening.close()
+                )
+            )
+            .with_span(token.range())
+            .with_label(
+                Label::new((src.id.clone(), last.0.byte()..(last.0.byte() + 1)))
+                    .with_color(Fixed(86))
+                    .with_message(
+                        format!("Opening delimiter '{}{}", 
+                            opening.open().fg(Red), "' occurred here.".fg(Cyan)).fg(Cyan))
+                    .with_order(1)
+            )
+            .return_error(src, token.start(),
+            format!("Invalid closing '{}{}.", token.fg(Fixed(81)), "' here".fg(Red)).fg(Red).to_string())
+    }
+
+    fn missing_quoted_datum(src: &SrcInfo, (pos, kind): &(SourcePos, TokenKind)) -> Error {
+        let text = if let TokenKind::Quote(prefix) = kind { prefix.text() } else { "" };
+        Error::new(ErrorKind::InvalidSyntax)
+            .with_message(format!("No datum follows the quote prefix '{text}'."))
+            .with_span(pos.byte()..(pos.byte() + text.len()))
+            .return_error(src, *pos, "Quote prefix here.".to_string())
+    }
+
+    /// Read the value of an atom token, an invalid one is kept as a symbol
+    /// or a string along with the error.
+    pub(crate) fn read_atom(src: &SrcInfo, token: &Token) -> (NodeValue, Option<Error>) {
+        let (pos, range, text) = (token.start(), token.range(), token.text());
+        match token.kind() {
+            TokenKind::String => {
+                let content = &text[1..text.len()-1];
+                match Self::unescape(content) {
+                    Ok(unquoted) => (NodeValue::String(unquoted), None),
+                    Err((chars, message)) => (NodeValue::String(content.to_string()), Some(Error::new(ErrorKind::InvalidSyntax)
+                        .with_message(message)
+                        .with_span(Self::sub_range(&range, 1, content, chars))
+                        .return_error(src, pos, "Invalid escape sequence here.".to_string())))
                 }
+            },
+            TokenKind::Boolean => (NodeValue::Boolean(matches!(text, "#t" | "#true")), None),
+            TokenKind::Char => match parse_char(&text[2..]) {
+                Ok(ch) => (NodeValue::Char(ch), None),
+                Err(message) => (NodeValue::Symbol(text.into()),
+                    Some(Self::invalid_token(src, pos, text, message, "Invalid character literal")))
+            },
+            TokenKind::Number => {
+                let error = NumberLiteral::parse(text).err().map(|(chars, message)| {
+                    let chars = chars.start..chars.end.max(chars.start + 1);
+                    Error::new(ErrorKind::InvalidSyntax)
+                        .with_message(message)
+                        .with_span(Self::sub_range(&range, 0, text, chars))
+                        .return_error(src, pos, format!("Malformed number '{text}'."))
+                });
+                (NodeValue::Number(text.to_string()), error)
+            }
+            TokenKind::Constant => match text {
+                "#inert" => (NodeValue::Inert, None),
+                "#ignore" => (NodeValue::Ignore, None),
+                _ => (NodeValue::Symbol(text.into()), Some(Self::invalid_token(src, pos, text,
+                    format!("Unknown constant '{text}'."), "Invalid constant")))
+            },
+            _ => match Symbol::try_from(token.clone()) {
+                Ok(symbol) => (NodeValue::Symbol(symbol), None),
+                Err(err) => (NodeValue::Symbol(text.into()),
+                    Some(Self::invalid_token(src, pos, text, err.message().to_string(), "Invalid symbol")))
             }
         }
+    }
 
-        Ok(if nest.0 != 0 {
-            let last = nest.1.last().unwrap();
-            return Err(Error::new(ErrorKind::InvalidSyntax)
-                .with_message(
-                    format!("No corresponding '{}' for '{}' was found.", Token(last.1.clone()).as_right_parentheses(), last.1))
-                .with_span((last.0.i()-1)..last.0.i())
-                .return_error(&src, last.0,
-                    format!("Single '{}' found here.", last.1.clone().fg(Color::Red))));
-        })
+    /// Report an invalid token starting at the position.
+    fn invalid_token(src: &SrcInfo, pos: SourcePos, token: &str, message: String, label: &str) -> Error {
+        Error::new(ErrorKind::InvalidSyntax)
+            .with_message(message)
+            .with_span(pos.byte()..(pos.byte() + token.len()))
+            .return_error(src, pos, format!("{label} '{token}'."))
+    }
+
+    /// The byte range of the characters `chars` in the part of a token
+    /// starting at the byte `offset` of the token.
+    fn sub_range(token: &Range<usize>, offset: usize, part: &str, chars: Range<usize>) -> Range<usize> {
+        let bytes = SourcePos::byte_range(part, chars);
+        (token.start + offset + bytes.start)..(token.start + offset + bytes.end)
     }
 
+    fn missing_commented_datum(src: &SrcInfo, pos: SourcePos) -> Error {
+        Error::new(ErrorKind::InvalidSyntax)
+            .with_message("No datum follows the datum comment.".to_string())
+            .with_span(pos.byte()..(pos.byte() + 2))
+            .return_error(src, pos, "Datum comment here.".to_string())
+    }
+
+    /// Remove the quotes of a string literal and process its escapes.
     pub fn try_unquote(s: &str) -> Result<String, Error> {
-        let first = s.chars().nth(0).unwrap();
-        let end = s.chars().last().unwrap();
-        if first == end {
-            Ok(s[1..s.len()-1].to_string())
-        } else { Err(Error::new(ErrorKind::InvalidSyntax)) }
+        let mut chars = s.chars();
+        match (chars.next(), chars.next_back()) {
+            (Some('"'), Some('"')) =>
+                Self::unescape(chars.as_str()).map_err(|(_, message)| Error::new(ErrorKind::InvalidSyntax)
+                    .with_message(message)),
+            _ => Err(Error::new(ErrorKind::InvalidSyntax)
+                .with_message(format!("Expected a string literal, but found '{s}'.")))
+        }
+    }
+
+    /// Process the escapes in the content of a string literal, the error is
+    /// the character range of the invalid escape and the reason.
+    ///
+    /// Supported escapes are `\n \t \r \a \b \0 \\ \" \'`, `\x41;` and `\u{41}`
+    /// for code points, and a backslash at the end of a line, which skips
+    /// the line break along with the surrounding spaces.
+    fn unescape(content: &str) -> Result<String, (std::ops::Range<usize>, String)> {
+        let chars: Vec<char> = content.chars().collect();
+        let mut unescaped = String::with_capacity(content.len());
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] != '\\' {
+                seq!(unescaped.push(chars[i]), i += 1);
+                continue
+            }
+            let start = i;
+            i += 1;
+            let Some(&escape) = chars.get(i) else {
+                return Err((start..i, "Expected an escaped character after '\\'.".to_string()))
+            };
+            i += 1;
+            let ch = match escape {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                'a' => '\x07',
+                'b' => '\x08',
+                '0' => '\0',
+                '\\' | '"' | '\'' => escape,
+                'x' | 'u' => {
+                    let (open, close) = if_or!(escape == 'x', (None, ';'), (Some('{'), '}'));
+                    if open.is_some() {
+                        if chars.get(i) != open.as_ref() {
+                            return Err((start..i, "Expected '{' after '\\u'.".to_string()))
+                        }
+                        i += 1;
+                    }
+                    let digits_start = i;
+                    while chars.get(i).is_some_and(char::is_ascii_hexdigit) { i += 1 }
+                    let digits: String = chars[digits_start..i].iter().collect();
+                    if chars.get(i) != Some(&close) {
+                        return Err((start..i, format!("Expected '{close}' to close the escape '\\{escape}'.")))
+                    }
+                    i += 1;
+                    match u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32) {
+                        Some(ch) => ch,
+                        None => return Err((start..i, format!("'{digits}' is not a valid Unicode scalar value.")))
+                    }
+                },
+                ' ' | '\t' | '\n' | '\r' => {
+                    // A line continuation.
+                    i -= 1;
+                    while chars.get(i).is_some_and(|ch| matches!(ch, ' ' | '\t')) { i += 1 }
+                    match chars.get(i) {
+                        Some('\n') => i += 1,
+                        Some('\r') if chars.get(i + 1) == Some(&'\n') => i += 2,
+                        _ => return Err((start..i, "Expected a line break after '\\' and spaces.".to_string()))
+                    }
+                    while chars.get(i).is_some_and(|ch| matches!(ch, ' ' | '\t')) { i += 1 }
+                    continue
+                },
+                escape => return Err((start..i, format!("Unknown escape sequence '\\{escape}'.")))
+            };
+            unescaped.push(ch);
+        }
+        Ok(unescaped)
     }
 
-    // TODO: Update
+    /// Read the tokens into the tree without source locations or
+    /// diagnostics, unmatched closing delimiters are skipped and the
+    /// unclosed lists are closed at the end.
     pub fn parse_untraced(&mut self, tokens: Vec<Token>) {
-        let mut nest: (i32, Vec<String>) = (0, vec![]); // (Nesting Depth, Parentheses Kind)
-        let mut current = &mut self.tree;
+        let mut nest = TreeBuilder::new(core::mem::replace(&mut self.tree, NodeValue::List(vec![]).into()));
 
         for token in tokens {
-            match token.0.as_str() {
-                "(" | "[" | "{" => {
-                    nest.0 += 1;
-                    nest.1.push(token.0.to_string());
-                    current = current.push(Node::List(vec![]));
-                }
-                ")" | "]" | "}" => {
-                    nest.0 -= 1;
-                    let _last = nest.1.last().unwrap_or_else(|| {
-                        panic!("{}", 
-                        Error::new(ErrorKind::InvalidSyntax)
-                                .with_message(format!("No corresponding '{token}' can be found.")))
-                    });
-                    // TODO: Adjust implementation.
-                    // let _ = token.match_left_parentheses(last).is_err_and(|err| panic!("{err}"));
-                    nest.1.pop();
-                    current = &mut self.tree;
-                    for _ in 0..nest.0 {
-                        if let Node::List(ref mut list) = current {
-                            current = list.last_mut().unwrap();
-                        }
-                    }
-                }
+            match token.kind() {
+                TokenKind::Open(delimiter) =>
+                    nest.open((token.start(), token.kind()), Node::delimited(delimiter, vec![])),
+                TokenKind::Close(_) => nest.close(0, false),
+                TokenKind::Comment | TokenKind::Whitespace => {}
                 _ => {
-                    let symbol = Symbol::try_from(token);
-                    current.push(Node::Symbol(symbol.unwrap_or_else(|err| panic!("{err}"))));
+                    let symbol = Symbol::try_from(token.clone()).unwrap_or_else(|_| token.text().into());
+                    nest.current().push(NodeValue::Symbol(symbol).into());
                 }
             }
         }
+        self.tree = nest.finish(0);
     }
 
     pub fn reset(mut self) -> Node {
-        core::mem::replace(&mut self.tree, Node::List(vec![]))
+        core::mem::replace(&mut self.tree, NodeValue::List(vec![]).into())
     }
     
     pub fn tree(self) -> Node {
@@ -350,18 +947,331 @@ impl SyntacticParser {
 
 }
 
-#[allow(unused)]
-pub struct InfixTransformer {}
+/// How a chain of infix operators of the same precedence is grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right
+}
+
+/// An operator of curly-infix expressions, which is transformed into the
+/// combiner named `name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operator {
+    pub precedence: u8,
+    pub associativity: Associativity,
+    pub name: Symbol
+}
+
+/// A sequence being transformed, with the nodes left and the elements
+/// transformed. The tail of a dotted list is transformed last.
+struct Walk {
+    delimiter: Delimiter,
+    /// Whether the elements can be neoteric expressions.
+    neoteric: bool,
+    nodes: std::vec::IntoIter<Node>,
+    tail: Option<Node>,
+    elements: Vec<Node>,
+    /// How the node being transformed is merged with the element before
+    /// it: its opening delimiter if it's neoteric, whether they're adjacent
+    /// and whether it's `{}`. It's absent for the tail.
+    merge: Option<(Option<Delimiter>, bool, bool)>,
+    walked_tail: Option<Node>,
+    span: Option<Span>
+}
+
+impl Walk {
+    fn new(delimiter: Delimiter, neoteric: bool, nodes: Vec<Node>, tail: Option<Node>, span: Option<Span>) -> Self {
+        Self {
+            delimiter, neoteric, nodes: nodes.into_iter(), tail, elements: vec![], merge: None, walked_tail: None, span
+        }
+    }
+
+    /// Take the next node to be transformed.
+    fn next(&mut self, src: &SrcInfo) -> Option<Node> {
+        let Some(node) = self.nodes.next() else {
+            self.merge = None;
+            return self.tail.take()
+        };
+        let opening = InfixTransformer::opening(src, &node).filter(|_| self.neoteric);
+        let adjacent = match (self.elements.last().and_then(Node::span), node.span()) {
+            (Some(last), Some(span)) => last.end() == span.start(),
+            _ => false
+        };
+        let empty = matches!(node.value(), NodeValue::Curly(nodes) if nodes.is_empty());
+        self.merge = Some((opening, adjacent, empty));
+        Some(node)
+    }
+
+    /// Add the transformed node, a neoteric expression is merged with the
+    /// element before it.
+    fn add(&mut self, node: Node) {
+        let Some((opening, adjacent, empty)) = self.merge else {
+            return self.walked_tail = Some(node)
+        };
+        match (opening, self.elements.pop()) {
+            (Some(opening), Some(last)) if adjacent && opening != Delimiter::Bracket =>
+                self.elements.push(InfixTransformer::apply(last, node, opening == Delimiter::Paren || empty)),
+            (_, last) => seq!(self.elements.extend(last), self.elements.push(node))
+        }
+    }
+
+    fn finish(self, transformer: &InfixTransformer, src: &SrcInfo) -> Result<Node, Error> {
+        let value = match (self.delimiter, self.walked_tail) {
+            (Delimiter::Brace, _) => return transformer.curly(src, self.elements, self.span),
+            (Delimiter::Bracket, _) => NodeValue::Vector(self.elements),
+            (_, Some(tail)) => NodeValue::DottedList(self.elements, Box::new(tail)),
+            _ => NodeValue::List(self.elements)
+        };
+        Ok(Node { value, span: self.span })
+    }
+}
+
+/// Transform the curly-infix expressions of SRFI-105 into prefix lists.
+///
+/// `{a op b op c}` with a single operator becomes `(op a b c)`, `{}`,
+/// `{e}` and `{op e}` become `()`, `e` and `(op e)`. Mixed operators are
+/// grouped by the precedence table, and rejected if any of them is not
+/// in it. Inside the braces `f(x y)` is read as `(f x y)` and `f{...}` as
+/// `(f {...})`, when no whitespace separates them.
+#[derive(Debug, Clone)]
+pub struct InfixTransformer {
+    operators: HashMap<String, Operator>
+}
+
+impl Default for InfixTransformer {
+    /// The logical, comparison and arithmetic operators, transformed into
+    /// the corresponding combiners of the ground environment.
+    fn default() -> Self {
+        use Associativity::*;
+        let mut transformer = Self::new()
+            .with_operator("or", 1, Left, "$or?")
+            .with_operator("and", 2, Left, "$and?");
+        for comparison in ["=", "<", "<=", ">", ">="] {
+            let name = format!("{comparison}?");
+            transformer = transformer
+                .with_operator(comparison, 3, Left, &name)
+                .with_operator(&name, 3, Left, &name);
+        }
+        transformer
+            .with_operator("+", 4, Left, "+")
+            .with_operator("-", 4, Left, "-")
+            .with_operator("*", 5, Left, "*")
+            .with_operator("/", 5, Left, "/")
+            .with_operator("^", 6, Right, "expt")
+    }
+}
+
+impl InfixTransformer {
+    /// A transformer with an empty precedence table, which only accepts
+    /// the infix expressions with a single operator.
+    pub fn new() -> Self {
+        Self { operators: HashMap::new() }
+    }
+
+    /// Add the operator `symbol` to the precedence table, the operators of
+    /// higher precedence are grouped first.
+    pub fn with_operator(mut self, symbol: &str, precedence: u8, associativity: Associativity, name: &str) -> Self {
+        let operator = Operator { precedence, associativity, name: Symbol::new(name) };
+        seq!(self.operators.insert(symbol.to_string(), operator), self)
+    }
+
+    pub fn operator(&self, symbol: &str) -> Option<&Operator> {
+        self.operators.get(symbol)
+    }
+
+    /// Transform all the curly-infix expressions in the tree read from
+    /// the source.
+    pub fn transform(&self, src: &SrcInfo, node: Node) -> Result<Node, Error> {
+        // The sequences being transformed, from the outermost one.
+        let mut stack: Vec<Walk> = vec![];
+        let (mut node, mut walked) = (Some(node), None);
+        loop {
+            if let Some(node) = node.take() {
+                // The lists made by quote prefixes don't start with a delimiter.
+                let neoteric = stack.last().is_some_and(|walk| walk.neoteric) && Self::opening(src, &node).is_some();
+                match node.into_parts() {
+                    (NodeValue::Curly(nodes), span) => stack.push(Walk::new(Delimiter::Brace, true, nodes, None, span)),
+                    (NodeValue::List(nodes), span) => stack.push(Walk::new(Delimiter::Paren, neoteric, nodes, None, span)),
+                    (NodeValue::Vector(nodes), span) =>
+                        stack.push(Walk::new(Delimiter::Bracket, neoteric, nodes, None, span)),
+                    (NodeValue::DottedList(nodes, tail), span) =>
+                        stack.push(Walk::new(Delimiter::Paren, neoteric, nodes, Some(*tail), span)),
+                    (value, span) => walked = Some(Node { value, span })
+                }
+            }
+            let Some(walk) = stack.last_mut() else {
+                return Ok(walked.unwrap_or_else(|| Node::list(vec![])))
+            };
+            if let Some(walked) = walked.take() { walk.add(walked) }
+            node = walk.next(src);
+            if node.is_none() {
+                if let Some(walk) = stack.pop() { walked = Some(walk.finish(self, src)?) }
+            }
+        }
+    }
+
+    /// Combine `f` with the operands of the neoteric expression `f(...)`,
+    /// or with `arg` as a single operand.
+    fn apply(f: Node, arg: Node, spread: bool) -> Node {
+        let range = f.span().zip(arg.span()).map(|(f, arg)| Span::new(f.id.clone(), f.start()..arg.end()));
+        let node = match arg.into_parts() {
+            (NodeValue::List(mut nodes), _) if spread => seq!(nodes.insert(0, f), Node::list(nodes)),
+            (NodeValue::DottedList(mut nodes, tail), _) if spread =>
+                seq!(nodes.insert(0, f), NodeValue::DottedList(nodes, tail).into()),
+            (value, span) => Node::list(vec![f, Node { value, span }])
+        };
+        match range {
+            Some(range) => node.with_span(range),
+            None => node
+        }
+    }
+
+    /// The delimiter opening the node in the source.
+    fn opening(src: &SrcInfo, node: &Node) -> Option<Delimiter> {
+        let span = node.span()?;
+        match src.text.get(span.start()..)?.chars().next()? {
+            '(' => Some(Delimiter::Paren),
+            '[' => Some(Delimiter::Bracket),
+            '{' => Some(Delimiter::Brace),
+            _ => None
+        }
+    }
+
+    /// Transform the curly-infix expression from its transformed elements.
+    fn curly(&self, src: &SrcInfo, mut nodes: Vec<Node>, span: Option<Span>) -> Result<Node, Error> {
+        let node = match nodes.len() {
+            0 => Node::list(nodes),
+            2 => {
+                // A prefix operator is transformed as the one between operands.
+                nodes[0] = self.rename(&nodes[0]);
+                Node::list(nodes)
+            },
+            1 => return Ok(nodes.pop().unwrap()),
+            len if len % 2 == 0 => return Err(Self::error(src, span.as_ref(),
+                "Expected an operator between every two operands of the infix expression.".to_string(),
+                "Infix expression here.")),
+            _ => {
+                let mut operands = vec![];
+                let mut operators = vec![];
+                for (i, node) in nodes.into_iter().enumerate() {
+                    if i % 2 == 0 { operands.push(node) } else { operators.push(node) }
+                }
+                self.group(src, operands, operators)?
+            }
+        };
+        Ok(match span {
+            Some(span) => node.with_span(span),
+            None => node
+        })
+    }
+
+    /// Group the operands by the operators between them.
+    fn group(&self, src: &SrcInfo, operands: Vec<Node>, operators: Vec<Node>) -> Result<Node, Error> {
+        let symbols = operators.iter().map(|node| match node.value() {
+            NodeValue::Symbol(symbol) => Ok(symbol.to_string()),
+            _ => Err(Self::error(src, node.span(),
+                format!("Expected an operator of the infix expression, but found '{node}'."), "Not an operator."))
+        }).collect::<Result<Vec<_>, _>>()?;
+        let uniform = symbols.iter().all(|symbol| *symbol == symbols[0]);
+        let right = self.operator(&symbols[0]).is_some_and(|op| op.associativity == Associativity::Right);
+        if uniform && !right {
+            let name = self.rename(&operators[0]);
+            return Ok(Node::list([name].into_iter().chain(operands).collect()))
+        }
+
+        let mut table: Vec<&Operator> = vec![];
+        for (symbol, node) in symbols.iter().zip(&operators) {
+            let Some(op) = self.operator(symbol) else {
+                let other = symbols.iter().find(|other| *other != symbol).unwrap_or(symbol);
+                return Err(Self::error(src, node.span(),
+                    format!("Mixed operators '{symbol}' and '{other}' without precedence in the infix expression."),
+                    "No precedence for this operator."))
+            };
+            let ambiguous = table.iter().zip(&symbols)
+                .find(|(other, _)| other.precedence == op.precedence && other.associativity != op.associativity);
+            if let Some((_, other)) = ambiguous {
+                return Err(Self::error(src, node.span(),
+                    format!("Operators '{other}' and '{symbol}' have the same precedence but different associativity."),
+                    "Ambiguous operator."))
+            }
+            table.push(op);
+        }
+
+        let mut operands = operands.into_iter();
+        let mut outputs = vec![(operands.next().unwrap(), None)];
+        let mut stack: Vec<(&Operator, Node)> = vec![];
+        for ((op, node), operand) in table.into_iter().zip(operators).zip(operands) {
+            while let Some((top, _)) = stack.last() {
+                if top.precedence < op.precedence
+                    || top.precedence == op.precedence && op.associativity == Associativity::Right { break }
+                let (top, node) = stack.pop().unwrap();
+                Self::reduce(&mut outputs, top, node);
+            }
+            stack.push((op, node));
+            outputs.push((operand, None));
+        }
+        while let Some((top, node)) = stack.pop() {
+            Self::reduce(&mut outputs, top, node);
+        }
+        Ok(outputs.pop().unwrap().0)
+    }
+
+    /// Combine the last two operands by the operator, the operands are
+    /// paired with the operators making them, so that `{a * b * c + d}`
+    /// becomes `(+ (* a b c) d)`.
+    fn reduce<'a>(outputs: &mut Vec<(Node, Option<&'a Operator>)>, op: &'a Operator, node: Node) {
+        let (b, _) = outputs.pop().unwrap();
+        let (a, made_by) = outputs.pop().unwrap();
+        let range = a.span().zip(b.span()).map(|(a, b)| Span::new(a.id.clone(), a.start()..b.end()));
+        let mut list = match a.into_parts() {
+            (NodeValue::List(mut nodes), _) if made_by == Some(op) && op.associativity == Associativity::Left =>
+                seq!(nodes.push(b), Node::list(nodes)),
+            (value, span) => {
+                let name = Node { value: NodeValue::Symbol(op.name.clone()), span: node.into_parts().1 };
+                Node::list(vec![name, Node { value, span }, b])
+            }
+        };
+        if let Some(range) = range { list.set_span(range) }
+        outputs.push((list, Some(op)));
+    }
+
+    /// Replace the operator by the combiner it's transformed into.
+    fn rename(&self, node: &Node) -> Node {
+        match node.value() {
+            NodeValue::Symbol(symbol) => match self.operator(symbol.as_ref()) {
+                Some(op) => Node { value: NodeValue::Symbol(op.name.clone()), span: node.span.clone() },
+                None => node.clone()
+            },
+            _ => node.clone()
+        }
+    }
 
-impl InfixTransformer {}
+    fn error(src: &SrcInfo, span: Option<&Span>, message: String, label: &str) -> Error {
+        let err = Error::new(ErrorKind::InvalidSyntax).with_message(message);
+        match span {
+            Some(span) => err.with_span(span.range())
+                .return_error(src, SourcePos::locate(&src.text, span.start()), label.to_string()),
+            None => err
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use crate::{share, syntax::Node};
-    use super::{SrcInfo, LexicalParser, SyntacticParser, Token};
+    use crate::share;
+    use crate::error::Error;
+    use crate::syntax::{Node, NodeValue};
+    use super::{Associativity, Delimiter, InfixTransformer, SrcInfo, LexicalParser, SourcePos, SyntacticParser, Token};
 
-    fn to_tokens(vector: Vec<&str>) -> Vec<Token> {
-        vector.into_iter().map(|string| string.into()).collect()
+    fn texts(lexer: &LexicalParser) -> Vec<String> {
+        lexer.tokens().into_iter().map(String::from).collect()
+    }
+
+    /// Read the source into a tree, or the first error of it.
+    fn parse(source: &str) -> Result<Node, Error> {
+        let mut parser = SyntacticParser::new(share!(SrcInfo::new("test", source)));
+        parser.try_parse().map(|_| parser.tree()).map_err(|mut errors| errors.remove(0))
     }
 
     #[test]
@@ -369,10 +1279,10 @@ mod tests {
         let mut lexer;
         lexer = LexicalParser::new();
         lexer.parse_str("($if #t #t #f)");
-        assert_eq!(*lexer.tokens(), to_tokens(vec!["(", "$if", "#t", "#t", "#f", ")"]));
+        assert_eq!(texts(&lexer), vec!["(", "$if", "#t", "#t", "#f", ")"]);
         lexer = LexicalParser::new();
         lexer.parse_str("(eval     ())\n(display)");
-        assert_eq!(*lexer.tokens(), to_tokens(vec!["(", "eval", "(", ")", ")", "(", "display", ")"]));
+        assert_eq!(texts(&lexer), vec!["(", "eval", "(", ")", ")", "(", "display", ")"]);
     }
 
     #[test]
@@ -380,42 +1290,347 @@ mod tests {
         let mut lexer: LexicalParser;
         lexer = LexicalParser::new();
         lexer.parse_str(r#"($if "test=parsing" #t)"#);
-        assert_eq!(lexer.tokens(), to_tokens(vec!["(", "$if", "\"test=parsing\"", "#t", ")"]))
+        assert_eq!(texts(&lexer), vec!["(", "$if", "\"test=parsing\"", "#t", ")"])
     }
 
     #[test]
     fn lexical_parse_int() {
         let mut lexer = LexicalParser::new();
         lexer.parse_str("($lambda (f) (f (+ 0 1)))");
-        assert_eq!(lexer.tokens(),
-            to_tokens(vec!["(", "$lambda", "(", "f", ")", "(", "f", "(", "+", "0", "1", ")", ")", ")"]));
+        assert_eq!(texts(&lexer),
+            vec!["(", "$lambda", "(", "f", ")", "(", "f", "(", "+", "0", "1", ")", ")", ")"]);
+    }
+
+    #[test]
+    fn lexical_parse_comments() {
+        let mut lexer = LexicalParser::new();
+        lexer.parse_str("(a ; (b)\n c;d\n)");
+        assert_eq!(texts(&lexer), vec!["(", "a", "c", ")"]);
+        lexer = LexicalParser::new();
+        lexer.parse_str("a #| b #| (c) |# d |# e #|f|#");
+        assert_eq!(texts(&lexer), vec!["a", "e"]);
+        lexer = LexicalParser::new();
+        lexer.parse_str("#| |# (x) #;y");
+        assert_eq!(texts(&lexer), vec!["(", "x", ")", "#;", "y"]);
+        // Positions after a comment are still tracked by characters.
+        assert_eq!(lexer.tokens()[0].start(), SourcePos::locate("#| |# (x)", 6));
+        lexer = LexicalParser::new();
+        lexer.parse_str("a #| b |");
+        assert_eq!(lexer.unterminated_comment().map(|pos| (pos.ln(), pos.col(), pos.byte())), Some((1, 3, 2)));
+    }
+
+    #[test]
+    fn syntactic_parse_comments() {
+        assert_eq!(parse("; header\n(a #| (b) |# c) ; trailer").unwrap(),
+            Node::list(vec![Node::list(vec!["a".into(), "c".into()])]));
+        assert_eq!(parse("(a #;(b c) d) #;e f").unwrap(),
+            Node::list(vec![Node::list(vec!["a".into(), "d".into()]), "f".into()]));
+        assert_eq!(parse("(#; #; a b c) (#;(#;x y) z)").unwrap(),
+            Node::list(vec![Node::list(vec!["c".into()]), Node::list(vec!["z".into()])]));
+        assert!(parse("(a #;)").is_err());
+        assert!(parse("a #;").is_err());
+        assert!(parse("a #| b").is_err());
+    }
+
+    #[test]
+    fn lexical_parse_strings() {
+        let mut lexer = LexicalParser::new();
+        lexer.parse_str("(f \"a b\\\" (c)\"x 'y)");
+        assert_eq!(texts(&lexer), vec!["(", "f", "\"a b\\\" (c)\"", "x", "'", "y", ")"]);
+        assert_eq!(lexer.unterminated_string(), None);
+        lexer = LexicalParser::new();
+        lexer.parse_str("(f \"a\nb\"\n\"c)");
+        let opening = lexer.unterminated_string().unwrap();
+        assert_eq!((opening.ln(), opening.col(), opening.byte()), (3, 1, 9));
+    }
+
+    #[test]
+    fn source_positions() {
+        let text = "ab\nλ好c\nd";
+        let pos = |byte| {
+            let pos = SourcePos::locate(text, byte);
+            (pos.ln(), pos.col(), pos.display_col(), pos.byte())
+        };
+        assert_eq!(pos(0), (1, 1, 1, 0));
+        assert_eq!(pos(3), (2, 1, 1, 3));
+        assert_eq!(pos(5), (2, 2, 2, 5));
+        // The wide character takes two columns in a terminal.
+        assert_eq!(pos(8), (2, 3, 4, 8));
+        assert_eq!(pos(10), (3, 1, 1, 10));
+        // An offset inside a character is moved to its start.
+        assert_eq!(pos(4), (2, 1, 1, 3));
+        assert_eq!(SourcePos::byte_range(text, 3..5), 3..8);
+        assert_eq!(SourcePos::byte_range(text, 8..20), 11..11);
+    }
+
+    #[test]
+    fn lexical_token_kinds() {
+        use super::{Delimiter::*, QuotePrefix::{Quasiquote, UnquoteSplicing}, TokenKind::*};
+        let source = "(f \"a\" ;c\n #|b|# [1 #\\x] ,@x `y #t #inert)";
+        let mut lexer = LexicalParser::new();
+        lexer.parse_str(source);
+        let results = lexer.results();
+        assert_eq!(results.iter().map(|token| token.kind()).collect::<Vec<_>>(), vec![
+            Open(Paren), Symbol, Whitespace, String, Whitespace, Comment, Whitespace, Comment, Whitespace,
+            Open(Bracket), Number, Whitespace, Char, Close(Bracket), Whitespace, Quote(UnquoteSplicing), Symbol,
+            Whitespace, Quote(Quasiquote), Symbol, Whitespace, Boolean, Whitespace, Constant, Close(Paren)
+        ]);
+        // The tokens including the trivia cover the whole source.
+        assert_eq!(results.iter().map(Token::text).collect::<std::string::String>(), source);
+        let newline = &results[6];
+        assert_eq!((newline.text(), newline.start().col(), newline.end().ln(), newline.end().col()), ("\n ", 10, 2, 2));
+        assert_eq!(results[15].range(), 25..27);
+    }
+
+    #[test]
+    fn lexical_parse_spans() {
+        let mut lexer = LexicalParser::new();
+        lexer.parse_str("(λ \"ab\" #;x)");
+        let ranges: Vec<_> = lexer.tokens().iter().map(Token::range).collect();
+        assert_eq!(ranges, vec![0..1, 1..3, 4..8, 9..11, 11..12, 12..13]);
+    }
+
+    #[test]
+    fn syntactic_unquote_strings() {
+        assert_eq!(SyntacticParser::try_unquote(r#""a\nb\t\\\"""#).unwrap(), "a\nb\t\\\"");
+        assert_eq!(SyntacticParser::try_unquote(r#""\x41;\u{3bb}\x1F600;""#).unwrap(), "Aλ😀");
+        assert_eq!(SyntacticParser::try_unquote("\"line \\  \n   continued\"").unwrap(), "line continued");
+        assert_eq!(SyntacticParser::try_unquote("\"multi\nline\"").unwrap(), "multi\nline");
+        assert!(SyntacticParser::try_unquote(r#""\q""#).is_err());
+        assert!(SyntacticParser::try_unquote(r#""\x41""#).is_err());
+        assert!(SyntacticParser::try_unquote(r#""\u{110000}""#).is_err());
+        assert!(SyntacticParser::try_unquote(r#""\ x""#).is_err());
+    }
+
+    #[test]
+    fn syntactic_parse_strings() {
+        use crate::error::ErrorKind;
+        assert_eq!(parse(r#"(display "a\nb")"#).unwrap(),
+            Node::list(vec![Node::list(vec!["display".into(), NodeValue::String("a\nb".into()).into()])]));
+        let err = parse("(display \"abc)\n(f)").unwrap_err();
+        assert_eq!((err.kind(), err.message().as_str()), (ErrorKind::InvalidSyntax, "The string literal is never closed."));
+        assert!(parse(r#"(display "a\qb")"#).unwrap_err().message().contains("\\q"));
+    }
+
+    #[test]
+    fn syntactic_parse_spans() {
+        let mut parser = SyntacticParser::new(share!(SrcInfo::new("test", "(f \"λ\" (g 1))")));
+        parser.try_parse().unwrap();
+        let tree = parser.tree();
+        let range = |node: &Node| node.span().map(|span| span.range());
+        let outer = &tree.as_ref()[0];
+        assert_eq!((range(&tree), range(outer)), (Some(0..14), Some(0..14)));
+        assert_eq!(outer.as_ref().iter().map(range).collect::<Vec<_>>(), vec![Some(1..2), Some(3..7), Some(8..13)]);
+        assert_eq!(range(&outer.as_ref()[2].as_ref()[1]), Some(11..12));
+        assert_eq!(outer.span().unwrap().id(), "test");
+    }
+
+    #[test]
+    fn syntactic_error_spans() {
+        let span = |source: &str| {
+            let mut parser = SyntacticParser::new(share!(SrcInfo::new("test", source)));
+            let err = parser.try_parse().unwrap_err().remove(0);
+            source[err.span()].to_string()
+        };
+        assert_eq!(span("(λ \"λ\\q\")"), "\\q");
+        assert_eq!(span("(λ 1λ)"), "λ");
+        assert_eq!(span("(λ #\\nope)"), "#\\nope");
+        assert_eq!(span("(λ ]"), "]");
+        assert_eq!(span("(λ #;)"), "#;");
+        assert_eq!(span("λ \"λ"), "\"");
+        assert_eq!(span("λ #| λ"), "#|");
+    }
+
+    #[test]
+    fn syntactic_error_recovery() {
+        let recover = |source: &str| {
+            let mut parser = SyntacticParser::new(share!(SrcInfo::new("test", source)));
+            let errors = parser.try_parse().err().unwrap_or_default().into_iter()
+                .map(|err| (err.message().to_string(), source[err.span()].to_string()))
+                .collect::<Vec<_>>();
+            (parser.tree().to_string(), errors)
+        };
+        let (tree, errors) = recover("(f 1x #\\nope)\n(g #unknown \"\\q\")\n(h . a b)");
+        assert_eq!(tree, "((f 1x #\\nope) (g #unknown \"\\\\q\") (h . a))");
+        assert_eq!(errors.iter().map(|(_, span)| span.as_str()).collect::<Vec<_>>(), vec!["x", "#\\nope", "#unknown", "\\q", "b"]);
+        // Unmatched closing delimiters are skipped, and missing ones are inserted.
+        assert_eq!(recover("(a ]) b)"), ("((a) b)".to_string(), vec![
+            ("')' is required, but only to found ']'".to_string(), "]".to_string()),
+            ("No corresponding '(' can be found for ')'.".to_string(), ")".to_string())
+        ]));
+        assert_eq!(recover("([a (b) c)").0, "(([a (b) c]))");
+        assert_eq!(recover("(a \'#;)").1, vec![
+            ("No datum follows the datum comment.".to_string(), "#;".to_string()),
+            ("No datum follows the quote prefix \'\'\'.".to_string(), "\'".to_string())
+        ]);
+        // The forms are resynchronized at the start of lines, if some
+        // delimiters are never closed.
+        assert_eq!(recover("(define (f x)\n  (g x)\n(define y 1)\n(f (g\n  y))"), ("((define (f x) (g x)) (define y 1) (f (g y)))".to_string(),
+            vec![("No corresponding ')' for '(' was found.".to_string(), "(".to_string())]));
+        assert_eq!(recover("(a\n(b))").1, vec![]);
+        assert_eq!(recover("(a \"b)\n(c)").1.iter().map(|(message, _)| message.as_str()).collect::<Vec<_>>(),
+            vec!["The string literal is never closed.", "No corresponding ')' for '(' was found."]);
+    }
+
+    #[test]
+    fn syntactic_parse_abbreviations() {
+        let list = |name: &str, node: Node| Node::list(vec![name.into(), node]);
+        assert_eq!(parse("'a `(b ,c ,@d)").unwrap(), Node::list(vec![
+            list("$quote", "a".into()),
+            list("$quasiquote", Node::list(vec!["b".into(), list("unquote", "c".into()), list("unquote-splicing", "d".into())]))
+        ]));
+        assert_eq!(parse("''a '#;b c").unwrap(), Node::list(vec![
+            list("$quote", list("$quote", "a".into())), list("$quote", "c".into())
+        ]));
+        assert_eq!(parse("(#;'a b)").unwrap(), Node::list(vec![Node::list(vec!["b".into()])]));
+        let tree = parse("(f '(a b))").unwrap();
+        let quoted = &tree.as_ref()[0].as_ref()[1];
+        assert_eq!(quoted.span().map(|span| span.range()), Some(3..9));
+        assert_eq!(quoted.as_ref()[0].span().map(|span| span.range()), Some(3..4));
+        assert_eq!(parse("(a ')").unwrap_err().message(), "No datum follows the quote prefix '''.");
+        assert_eq!(parse("a ,@").unwrap_err().message(), "No datum follows the quote prefix ',@'.");
+    }
+
+    #[test]
+    fn syntactic_parse_dotted_lists() {
+        let dotted = |nodes: Vec<Node>, tail: Node| Node::from(NodeValue::DottedList(nodes, Box::new(tail)));
+        assert_eq!(parse("(a . b) (a b . (c)) (x . #;y rest)").unwrap(), Node::list(vec![
+            dotted(vec!["a".into()], "b".into()),
+            dotted(vec!["a".into(), "b".into()], Node::list(vec!["c".into()])),
+            dotted(vec!["x".into()], "rest".into())
+        ]));
+        assert_eq!(parse("(a . 'b) (... .a)").unwrap(), Node::list(vec![
+            dotted(vec!["a".into()], Node::list(vec!["$quote".into(), "b".into()])),
+            Node::list(vec!["...".into(), ".a".into()])
+        ]));
+        assert_eq!(parse("((a . 1) (b . 2))").unwrap().to_string(), "(((a . 1) (b . 2)))");
+        let error = |source: &str| {
+            let err = parse(source).unwrap_err();
+            (err.message().to_string(), source[err.span()].to_string())
+        };
+        assert_eq!(error("(. a)"), ("Expected a datum before '.' in the list.".to_string(), ".".to_string()));
+        assert_eq!(error("(a . b c)"), ("Only one datum can follow '.' in the list.".to_string(), "c".to_string()));
+        assert_eq!(error("(a . b (c))"), ("Only one datum can follow '.' in the list.".to_string(), "(c)".to_string()));
+        assert_eq!(error("(a .)"), ("Expected a datum after '.' in the list.".to_string(), ".".to_string()));
+        assert_eq!(error("(a . b . c)"), ("Only one '.' can be placed in a list.".to_string(), ".".to_string()));
+        assert_eq!(error("a . b"), ("Unexpected '.' outside a list.".to_string(), ".".to_string()));
+        assert_eq!(error("('. a)"), ("Unexpected '.' outside a list.".to_string(), ".".to_string()));
+        assert_eq!(error("(a #; . b)"), ("No datum follows the datum comment.".to_string(), "#;".to_string()));
+    }
+
+    #[test]
+    fn syntactic_parse_brackets() {
+        assert_eq!(parse("(a [b] {c d})").unwrap(), Node::list(vec![Node::list(vec![
+            "a".into(),
+            NodeValue::Vector(vec!["b".into()]).into(),
+            NodeValue::Curly(vec!["c".into(), "d".into()]).into()
+        ])]));
+        assert_eq!(parse("[] {} [(a . b) {1 + 2}]").unwrap().to_string(), "([] {} [(a . b) {1 + 2}])");
+        assert_eq!(parse("[a . b]").unwrap_err().message(), "'.' can only be placed in a parenthesized list.");
+        assert_eq!(parse("{a . b}").unwrap_err().message(), "'.' can only be placed in a parenthesized list.");
+    }
+
+    #[test]
+    fn infix_transform() {
+        let transform = |transformer: &InfixTransformer, source: &str| {
+            let src = share!(SrcInfo::new("test", source));
+            let mut parser = SyntacticParser::new(src.clone());
+            parser.try_parse().unwrap();
+            let tree = transformer.transform(&src.borrow(), parser.tree());
+            tree.map(|tree| tree.to_string())
+        };
+        let infix = InfixTransformer::default();
+        assert_eq!(transform(&infix, "{a + b} {a + b + c} {a foo b foo c}").unwrap(), "((+ a b) (+ a b c) (foo a b c))");
+        assert_eq!(transform(&infix, "{} {a} {- a} {(a b)}").unwrap(), "(() a (- a) (a b))");
+        assert_eq!(transform(&infix, "{a + b * c} {a * b * c + d} {a - b + c}").unwrap(),
+            "((+ a (* b c)) (+ (* a b c) d) (+ (- a b) c))");
+        assert_eq!(transform(&infix, "{x > 0 and y < 10} {a or b and c}").unwrap(),
+            "(($and? (>? x 0) (<? y 10)) ($or? a ($and? b c)))");
+        assert_eq!(transform(&infix, "{and x} {or #t} {= x} {not x}").unwrap(), "(($and? x) ($or? #t) (=? x) (not x))");
+        assert_eq!(transform(&infix, "{2 ^ 3 ^ 2} {{a + b} * c} {(- a) - b}").unwrap(),
+            "((expt 2 (expt 3 2)) (* (+ a b) c) (- (- a) b))");
+        // Neoteric expressions are only read inside the braces.
+        assert_eq!(transform(&infix, "{f(x y) + g()} {f{a + b}} {f(x)(y)} (f(x) [a{b}])").unwrap(),
+            "((+ (f x y) (g)) (f (+ a b)) ((f x) y) (f (x) [a b]))");
+        assert_eq!(transform(&infix, "{f (x)} {f(a . b)} {'(a b) g{}}").unwrap(),
+            "((f (x)) (f a . b) (($quote (a b)) (g)))");
+        let custom = InfixTransformer::new().with_operator("<>", 1, Associativity::Left, "concat");
+        assert_eq!(transform(&custom, "{a <> b} {a + b}").unwrap(), "((concat a b) (+ a b))");
+
+        let error = |transformer: &InfixTransformer, source: &str| {
+            let err = transform(transformer, source).unwrap_err();
+            (err.message().to_string(), source[err.span()].to_string())
+        };
+        assert_eq!(error(&custom, "{a + b <> c}"),
+            ("Mixed operators '+' and '<>' without precedence in the infix expression.".to_string(), "+".to_string()));
+        assert_eq!(error(&infix, "{a + b xor c}"),
+            ("Mixed operators 'xor' and '+' without precedence in the infix expression.".to_string(), "xor".to_string()));
+        assert_eq!(error(&infix, "(f {a b c d})"),
+            ("Expected an operator between every two operands of the infix expression.".to_string(), "{a b c d}".to_string()));
+        assert_eq!(error(&infix, "{a + b (*) c}"),
+            ("Expected an operator of the infix expression, but found '(*)'.".to_string(), "(*)".to_string()));
+        let ambiguous = InfixTransformer::default().with_operator("++", 4, Associativity::Right, "append");
+        assert_eq!(error(&ambiguous, "{a + b ++ c}").0,
+            "Operators '+' and '++' have the same precedence but different associativity.");
+    }
+
+    #[test]
+    fn syntactic_parse_numbers() {
+        use NodeValue::*;
+        assert_eq!(parse("(- -1 1.5e3 1/2 #xff)").unwrap(), Node::list(vec![Node::list(vec![
+            "-".into(), Number("-1".into()).into(), Number("1.5e3".into()).into(),
+            Number("1/2".into()).into(), Number("#xff".into()).into()
+        ])]));
+        let err = parse("(+ 1 2x)").unwrap_err();
+        assert_eq!(err.message(), "Invalid digit 'x' for a number in radix 10.");
+    }
+
+    #[test]
+    fn lexical_parse_chars() {
+        let mut lexer = LexicalParser::new();
+        lexer.parse_str("(#\\( #\\) #\\  #\\; #\\space f(x))");
+        assert_eq!(texts(&lexer),
+            vec!["(", "#\\(", "#\\)", "#\\ ", "#\\;", "#\\space", "f", "(", "x", ")", ")"]);
+    }
+
+    #[test]
+    fn syntactic_parse_constants() {
+        use NodeValue::*;
+        assert_eq!(parse("#t #f #true #false #inert #ignore").unwrap(),
+            Node::list([Boolean(true), Boolean(false), Boolean(true), Boolean(false), Inert, Ignore]
+                .into_iter().map(Node::from).collect()));
+        assert_eq!(parse("(#\\a #\\( #\\space #\\x41 #\\x #\\λ)").unwrap(),
+            Node::list(vec![Node::list(['a', '(', ' ', 'A', 'x', 'λ'].into_iter().map(|ch| Char(ch).into()).collect())]));
+        assert_eq!(parse("#\\nope").unwrap_err().message(), "Unknown character name 'nope'.");
+        assert_eq!(parse("#\\xD800").unwrap_err().message(), "'D800' is not a valid Unicode scalar value.");
+        assert_eq!(parse("(#unknown)").unwrap_err().message(), "Unknown constant '#unknown'.");
     }
 
     #[test]
     fn syntactic_parse_tokens_untraced() {
-        use Node::*;
+        use NodeValue::*;
         let mut parser: SyntacticParser;
         
-        parser = SyntacticParser::new(share!(SrcInfo::new("test-1", "apply display +".into())));
+        parser = SyntacticParser::new(share!(SrcInfo::new("test-1", "apply display +")));
         parser.parse();
         assert_eq!(parser.tree(), 
-            List(vec![Symbol("apply".into()), Symbol("display".into()), Symbol("+".into())]));
+            Node::list(vec!["apply".into(), "display".into(), "+".into()]));
         
         parser = SyntacticParser::new(
             share!(SrcInfo::new(
                 "test-2",
-                "apply display (cons (list $if #t) [cons (list* #t #f) ()])".into()
+                "apply display (cons (list $if #t) [cons (list* #t #f) ()])"
             ))
         );
         parser.parse();
         assert_eq!(parser.tree(),
-            List(vec!["apply".into(), "display".into(), 
-                List(vec!["cons".into(), 
-                    List(vec!["list".into(), "$if".into(), "#t".into()]),
-                    List(vec!["cons".into(), 
-                        List(vec!["list*".into(), "#t".into(), "#f".into()]),
-                        List(vec![])]
-                    )
+            Node::list(vec!["apply".into(), "display".into(), 
+                Node::list(vec!["cons".into(), 
+                    Node::list(vec!["list".into(), "$if".into(), Boolean(true).into()]),
+                    Node::from(Vector(vec!["cons".into(), 
+                        Node::list(vec!["list*".into(), Boolean(true).into(), Boolean(false).into()]),
+                        Node::list(vec![])]
+                    ))
                 ])        
             ])
         );
@@ -423,7 +1638,6 @@ mod tests {
 
     #[test]
     fn syntactic_parse_tokens() {
-        use Node::*;
         let mut parser;
 
         parser = SyntacticParser::new(
@@ -434,17 +1648,81 @@ mod tests {
         );
         parser.try_parse().unwrap();
         assert_eq!(parser.tree(),
-            List(vec!["apply".into(), "+".into(), 
-                List(vec!["list".into(), 1.into(), 2.into()])
+            Node::list(vec!["apply".into(), "+".into(), 
+                Node::list(vec!["list".into(), 1.into(), 2.into()])
             ])
         );
     }
 
     #[test]
     fn syntactic_parse_parentheses_match() {
-        use Node::*;
         let mut parser = SyntacticParser::new(share!(SrcInfo::new("test-1", "([{}])")));
         parser.parse();
-        assert_eq!(parser.tree(), List(vec![List(vec![List(vec![List(vec![])])])]));
+        assert_eq!(parser.tree(), Node::list(vec![Node::list(vec![
+            Node::delimited(Delimiter::Bracket, vec![Node::delimited(Delimiter::Brace, vec![])])
+        ])]));
+    }
+
+    #[test]
+    fn syntactic_parse_deep_nesting() {
+        let depth = 2000;
+        let text = format!("{}{}", "(a ".repeat(depth), ")".repeat(depth));
+        let mut parser = SyntacticParser::new(share!(SrcInfo::new("test-1", text.as_str())));
+        parser.try_parse().unwrap();
+        let tree = parser.tree();
+        let mut node = &tree.as_ref()[0];
+        for level in 0..depth {
+            assert_eq!(node.span().map(super::Span::range), Some(3 * level..(text.len() - level)));
+            assert_eq!(node.as_ref()[0], Node::from("a"));
+            if level + 1 < depth { node = &node.as_ref()[1] }
+        }
+        // An unclosed list is closed at the end of the source.
+        let mut parser = SyntacticParser::new(share!(SrcInfo::new("test-1", "(a (b")));
+        assert!(parser.try_parse().is_err());
+        assert_eq!(parser.tree().to_string(), "((a (b)))");
+    }
+
+    /// Nodes which can be read from the source: symbols and numbers are
+    /// kept only if they're read as such.
+    fn readable_node() -> impl proptest::strategy::Strategy<Value = Node> {
+        use proptest::prelude::*;
+        use proptest::collection::vec;
+        use super::TokenKind;
+        use crate::syntax::NumberLiteral;
+        let number = prop_oneof![
+            any::<i64>().prop_map(|n| n.to_string()),
+            (any::<i32>(), 1..1000u32).prop_map(|(n, d)| format!("{n}/{d}")),
+            (any::<i32>(), 0..1000u32).prop_map(|(n, frac)| format!("{n}.{frac}")),
+            (0..0xFFFFu32).prop_map(|n| format!("#x{n:X}")),
+            Just("+inf.0".to_string())
+        ].prop_filter("a number", |n| TokenKind::of_atom(n) == TokenKind::Number && NumberLiteral::parse(n).is_ok());
+        let symbol = "[a-z+*/<>=!?$%&~^_.-][a-z0-9+*/<>=!?$%&~^_.@-]{0,8}"
+            .prop_filter("a symbol", |s| TokenKind::of_atom(s) == TokenKind::Symbol);
+        let atom = prop_oneof![
+            any::<bool>().prop_map(NodeValue::Boolean),
+            any::<char>().prop_map(NodeValue::Char),
+            Just(NodeValue::Ignore),
+            Just(NodeValue::Inert),
+            number.prop_map(NodeValue::Number),
+            any::<String>().prop_map(NodeValue::String),
+            symbol.prop_map(|s| NodeValue::Symbol(s.as_str().into()))
+        ].prop_map(Node::from);
+        atom.prop_recursive(6, 64, 6, |node| prop_oneof![
+            vec(node.clone(), 0..6).prop_map(Node::list),
+            vec(node.clone(), 0..6).prop_map(|nodes| Node::delimited(Delimiter::Bracket, nodes)),
+            vec(node.clone(), 0..6).prop_map(|nodes| Node::delimited(Delimiter::Brace, nodes)),
+            (vec(node.clone(), 1..6), node).prop_map(|(nodes, tail)| NodeValue::DottedList(nodes, Box::new(tail)).into())
+        ])
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn syntactic_parse_printed(forms in proptest::collection::vec(readable_node(), 0..6)) {
+            let tree = Node::list(forms);
+            let source = tree.to_source();
+            let mut parser = SyntacticParser::new(share!(SrcInfo::new("test", source.as_str())));
+            proptest::prop_assert!(parser.try_parse().is_ok(), "{source}");
+            proptest::prop_assert_eq!(parser.tree(), tree);
+        }
     }
 }
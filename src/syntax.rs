@@ -1,8 +1,9 @@
 use core::fmt::Display;
 
+use crate::{if_or, seq};
 use crate::error::{Error, ErrorKind};
-use crate::evaluation::Term;
-use crate::parser::Token;
+use crate::evaluation::{escape_char, escape_str, Number, Term};
+use crate::parser::{Delimiter, Span, Token};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Symbol(String);
@@ -59,111 +60,654 @@ impl TryFrom<Token> for Symbol {
     }
 }
 
+/// The value of a numeric literal, whose digits are stripped of the sign,
+/// the radix prefix and the `_` separators.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Node {
+pub enum NumberLiteral {
+    Integer { negative: bool, radix: u32, digits: String },
+    Rational { negative: bool, radix: u32, numerator: String, denominator: String },
+    /// A decimal in radix 10, normalized to be accepted by `f64::from_str`.
+    Decimal(String),
+}
+
+/// The error of a numeric literal is the character range of the invalid
+/// part and the reason.
+pub type NumberError = (std::ops::Range<usize>, String);
+
+impl NumberLiteral {
+    /// Whether the token is meant to be a number rather than a symbol, that
+    /// is it starts with a digit, a sign or a dot followed by a digit, or
+    /// a radix prefix.
+    pub fn is_numeric(token: &str) -> bool {
+        if matches!(token, "+inf.0" | "-inf.0" | "+nan.0" | "-nan.0") { return true }
+        let mut chars = token.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some('#'), Some('x' | 'X' | 'b' | 'B' | 'o' | 'O' | 'd' | 'D'), _) => true,
+            (Some(ch), _, _) if ch.is_ascii_digit() => true,
+            (Some('+' | '-'), Some('.'), Some(ch)) => ch.is_ascii_digit(),
+            (Some('+' | '-' | '.'), Some(ch), _) => ch.is_ascii_digit(),
+            _ => false
+        }
+    }
+
+    /// Parse a numeric literal of the form `[#x|#o|#b|#d][+|-]digits`,
+    /// optionally followed by `/digits` for a rational, or by a fraction
+    /// and an exponent for a decimal. Digits can be separated by `_`.
+    pub fn parse(token: &str) -> Result<Self, NumberError> {
+        let chars: Vec<char> = token.chars().collect();
+        let mut i = 0;
+        let radix = match chars.as_slice() {
+            ['#', prefix, ..] => {
+                i = 2;
+                match prefix.to_ascii_lowercase() {
+                    'x' => 16,
+                    'o' => 8,
+                    'b' => 2,
+                    'd' => 10,
+                    _ => return Err((0..2, format!("Unknown radix prefix '#{prefix}'.")))
+                }
+            },
+            _ => 10
+        };
+        match &token[i..] {
+            "+inf.0" => return Ok(Self::Decimal("inf".to_string())),
+            "-inf.0" => return Ok(Self::Decimal("-inf".to_string())),
+            "+nan.0" | "-nan.0" => return Ok(Self::Decimal("NaN".to_string())),
+            _ => {}
+        }
+        let negative = match chars.get(i) {
+            Some('-') => seq!(i += 1, true),
+            Some('+') => seq!(i += 1, false),
+            _ => false
+        };
+
+        let (integer, end) = Self::digits(&chars, i, radix)?;
+        i = end;
+        match chars.get(i) {
+            None if integer.is_empty() => Err((i..i, "Expected digits in the number.".to_string())),
+            None => Ok(Self::Integer { negative, radix, digits: integer }),
+            Some('/') => {
+                if integer.is_empty() {
+                    return Err((i..i + 1, "Expected digits before '/' in the rational.".to_string()))
+                }
+                let (denominator, end) = Self::digits(&chars, i + 1, radix)?;
+                match chars.get(end) {
+                    _ if denominator.is_empty() =>
+                        Err((i..i + 1, "Expected digits after '/' in the rational.".to_string())),
+                    Some(ch) => Err((end..end + 1, format!("Unexpected '{ch}' in the rational."))),
+                    None if denominator.chars().all(|ch| ch == '0') =>
+                        Err((i + 1..end, "The denominator of a rational can't be zero.".to_string())),
+                    None => Ok(Self::Rational { negative, radix, numerator: integer, denominator })
+                }
+            },
+            Some('.' | 'e' | 'E') if radix != 10 =>
+                Err((i..i + 1, "Only numbers in radix 10 can have a fraction or an exponent.".to_string())),
+            Some('.' | 'e' | 'E') => {
+                let mut fraction = String::new();
+                if chars[i] == '.' {
+                    (fraction, i) = Self::digits(&chars, i + 1, radix)?;
+                }
+                if integer.is_empty() && fraction.is_empty() {
+                    return Err((0..i, "Expected digits around the decimal point.".to_string()))
+                }
+                let mut exponent = "0".to_string();
+                if let Some('e' | 'E') = chars.get(i) {
+                    let start = i;
+                    let sign = match chars.get(i + 1) {
+                        Some(sign @ ('+' | '-')) => seq!(i += 1, *sign),
+                        _ => '+'
+                    };
+                    let digits;
+                    (digits, i) = Self::digits(&chars, i + 1, radix)?;
+                    if digits.is_empty() {
+                        return Err((start..i, "Expected digits in the exponent.".to_string()))
+                    }
+                    exponent = format!("{sign}{digits}");
+                }
+                if let Some(ch) = chars.get(i) {
+                    return Err((i..i + 1, format!("Unexpected '{ch}' in the decimal.")))
+                }
+                Ok(Self::Decimal(format!("{}{}.{}e{exponent}", if_or!(negative, "-", ""),
+                    if_or!(integer.is_empty(), "0", &integer), if_or!(fraction.is_empty(), "0", &fraction))))
+            },
+            Some(ch) if ch.is_alphanumeric() =>
+                Err((i..i + 1, format!("Invalid digit '{ch}' for a number in radix {radix}."))),
+            Some(ch) => Err((i..i + 1, format!("Unexpected '{ch}' in the number.")))
+        }
+    }
+
+    /// Collect the digits in the radix from `start`, which are separated by
+    /// single `_` characters, along with the index after them.
+    fn digits(chars: &[char], start: usize, radix: u32) -> Result<(String, usize), NumberError> {
+        let mut digits = String::new();
+        let mut i = start;
+        while let Some(&ch) = chars.get(i) {
+            if ch == '_' {
+                let valid = |ch: Option<&char>| ch.is_some_and(|ch| ch.is_digit(radix));
+                if i == start || !valid(chars.get(i - 1)) || !valid(chars.get(i + 1)) {
+                    return Err((i..i + 1, "A digit separator '_' must be placed between digits.".to_string()))
+                }
+            } else if ch.is_digit(radix) {
+                digits.push(ch);
+            } else {
+                break
+            }
+            i += 1;
+        }
+        Ok((digits, i))
+    }
+}
+
+/// Names of the characters written as `#\\name`.
+pub const CHAR_NAMES: [(&str, char); 9] = [
+    ("alarm", '\x07'), ("backspace", '\x08'), ("delete", '\x7f'), ("escape", '\x1b'), ("newline", '\n'),
+    ("null", '\0'), ("return", '\r'), ("space", ' '), ("tab", '\t')
+];
+
+/// Parse the part of a character literal after `#\\`, which is a single
+/// character, a name in `CHAR_NAMES` or `x` followed by a hex code point.
+pub fn parse_char(literal: &str) -> Result<char, String> {
+    let mut chars = literal.chars();
+    match (chars.next(), chars.as_str()) {
+        (None, _) => Err("Expected a character after '#\\'.".to_string()),
+        (Some(ch), "") => Ok(ch),
+        (Some('x' | 'X'), hex) if hex.chars().all(|ch| ch.is_ascii_hexdigit()) =>
+            u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+                .ok_or_else(|| format!("'{hex}' is not a valid Unicode scalar value.")),
+        _ => CHAR_NAMES.iter().find(|(name, _)| *name == literal).map(|(_, ch)| *ch)
+            .ok_or_else(|| format!("Unknown character name '{literal}'."))
+    }
+}
+
+/// A node of the syntax tree along with where it is read from.
+#[derive(Clone)]
+pub struct Node {
+    pub(crate) value: NodeValue,
+    pub(crate) span: Option<Span>
+}
+
+#[derive(Clone, Eq)]
+pub enum NodeValue {
+    Boolean(bool),
+    Char(char),
+    Ignore,
+    Inert,
     List(Vec<Node>),
+    /// `[...]`, read as a vector literal.
+    Vector(Vec<Node>),
+    /// `{...}`, reserved for curly-infix expressions.
+    Curly(Vec<Node>),
+    /// A list whose tail is the last node instead of `()`, as `(a b . c)`.
+    DottedList(Vec<Node>, Box<Node>),
     Number(String),
     String(String),
     Symbol(Symbol)
 }
 
 impl Node {
-    pub fn push(&mut self, node: Node) -> &mut Node{
-        self.as_mut().push(node);
-        match self {
-            Node::List(list) => list.last_mut().unwrap(),
-            _ => panic!()
+    /// Construct a list node without a source location.
+    pub fn list(nodes: Vec<Node>) -> Self {
+        NodeValue::List(nodes).into()
+    }
+
+    /// Construct an empty node of the sequence enclosed by the delimiter.
+    pub fn delimited(delimiter: Delimiter, nodes: Vec<Node>) -> Self {
+        match delimiter {
+            Delimiter::Paren => NodeValue::List(nodes).into(),
+            Delimiter::Bracket => NodeValue::Vector(nodes).into(),
+            Delimiter::Brace => NodeValue::Curly(nodes).into()
+        }
+    }
+
+    pub fn value(&self) -> &NodeValue { &self.value }
+
+    /// The delimiter enclosing the node, if it's a sequence.
+    pub fn delimiter(&self) -> Option<Delimiter> {
+        match self.value {
+            NodeValue::List(_) | NodeValue::DottedList(..) => Some(Delimiter::Paren),
+            NodeValue::Vector(_) => Some(Delimiter::Bracket),
+            NodeValue::Curly(_) => Some(Delimiter::Brace),
+            _ => None
         }
     }
 
+    /// The source location, which is absent for the nodes not read by
+    /// the parser.
+    pub fn span(&self) -> Option<&Span> { self.span.as_ref() }
+
+    pub fn set_span(&mut self, span: Span) { self.span = Some(span) }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        seq!(self.span = Some(span), self)
+    }
+
+    /// Print the forms of the tree read by the parser as source, one per
+    /// line, which is read back into the same tree.
+    pub fn to_source(&self) -> String {
+        self.as_ref().iter().map(|form| format!("{form}\n")).collect()
+    }
+
+    /// Take the value and the source location out of the node, which
+    /// can't be moved out directly since the node implements `Drop`.
+    pub fn into_parts(mut self) -> (NodeValue, Option<Span>) {
+        (core::mem::replace(&mut self.value, NodeValue::Inert), self.span.take())
+    }
+
+    /// Append the node to the sequence and return it, `None` is returned
+    /// if the node isn't a sequence.
+    pub fn push(&mut self, node: Node) -> Option<&mut Node> {
+        let (NodeValue::List(nodes) | NodeValue::Vector(nodes) | NodeValue::Curly(nodes)) = &mut self.value else {
+            return None
+        };
+        nodes.push(node);
+        nodes.last_mut()
+    }
+
+    /// Remove the last node of the sequence.
+    pub fn pop(&mut self) -> Option<Node> {
+        match &mut self.value {
+            NodeValue::List(nodes) | NodeValue::Vector(nodes) | NodeValue::Curly(nodes) => nodes.pop(),
+            _ => None
+        }
+    }
+}
+
+/// Nodes are compared regardless of their source locations.
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool { self.value == other.value }
 }
 
-impl AsMut<Vec<Node>> for Node {
-    fn as_mut(&mut self) -> &mut Vec<Node> {
-        match self {
-            Node::List(list) => list,
-            _ => panic!()
+/// The nested nodes are compared from an explicit stack instead of
+/// recursion.
+impl PartialEq for NodeValue {
+    fn eq(&self, other: &Self) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some(values) = stack.pop() {
+            let (a, b) = match values {
+                (NodeValue::List(a), NodeValue::List(b))
+                    | (NodeValue::Vector(a), NodeValue::Vector(b))
+                    | (NodeValue::Curly(a), NodeValue::Curly(b)) => (a, b),
+                (NodeValue::DottedList(a, x), NodeValue::DottedList(b, y)) => seq!(stack.push((&x.value, &y.value)), (a, b)),
+                (NodeValue::Boolean(a), NodeValue::Boolean(b)) if a == b => continue,
+                (NodeValue::Char(a), NodeValue::Char(b)) if a == b => continue,
+                (NodeValue::Ignore, NodeValue::Ignore) | (NodeValue::Inert, NodeValue::Inert) => continue,
+                (NodeValue::Number(a), NodeValue::Number(b)) | (NodeValue::String(a), NodeValue::String(b)) if a == b => continue,
+                (NodeValue::Symbol(a), NodeValue::Symbol(b)) if a == b => continue,
+                _ => return false
+            };
+            if a.len() != b.len() { return false }
+            stack.extend(a.iter().zip(b).map(|(a, b)| (&a.value, &b.value)));
         }
+        true
     }
 }
 
+impl Eq for Node {}
 
-impl AsRef<Vec<Node>> for Node {
-    fn as_ref(&self) -> &Vec<Node> {
-        match self {
-            Self::List(vec) => vec,
-            _ => panic!()
+/// The nodes of a sequence, other nodes have none.
+impl AsMut<[Node]> for Node {
+    fn as_mut(&mut self) -> &mut [Node] {
+        match &mut self.value {
+            NodeValue::List(list) | NodeValue::Vector(list) | NodeValue::Curly(list) => list,
+            _ => &mut []
         }
     }
 }
 
+impl AsRef<[Node]> for Node {
+    fn as_ref(&self) -> &[Node] {
+        match &self.value {
+            NodeValue::List(vec) | NodeValue::Vector(vec) | NodeValue::Curly(vec) => vec,
+            _ => &[]
+        }
+    }
+}
+
+/// A piece of the output of a tree. The nested nodes are written from an
+/// explicit stack instead of recursive calls, so that a tree of arbitrary
+/// depth can be printed.
+enum Piece<'a> {
+    Node(&'a Node),
+    Value(&'a NodeValue),
+    Text(&'static str),
+    Debug(&'a dyn std::fmt::Debug)
+}
+
+impl Piece<'_> {
+    /// Write the piece and the nested ones, in the form of `Debug` if
+    /// `debug` is set and of `Display` otherwise.
+    fn write(self, f: &mut std::fmt::Formatter<'_>, debug: bool) -> std::fmt::Result {
+        let mut stack = vec![self];
+        while let Some(piece) = stack.pop() {
+            let value = match piece {
+                Piece::Node(node) if debug => {
+                    f.write_str("Node { value: ")?;
+                    stack.extend([Piece::Text(" }"), Piece::Debug(&node.span), Piece::Text(", span: ")]);
+                    &node.value
+                },
+                Piece::Node(node) => &node.value,
+                Piece::Value(value) => value,
+                Piece::Text(text) => seq!(f.write_str(text)?, continue),
+                Piece::Debug(value) => seq!(write!(f, "{value:?}")?, continue)
+            };
+            let (open, nodes, tail, close) = match value {
+                NodeValue::List(nodes) => (if_or!(debug, "List([", "("), nodes, None, if_or!(debug, "])", ")")),
+                NodeValue::Vector(nodes) => (if_or!(debug, "Vector([", "["), nodes, None, if_or!(debug, "])", "]")),
+                NodeValue::Curly(nodes) => (if_or!(debug, "Curly([", "{"), nodes, None, if_or!(debug, "])", "}")),
+                NodeValue::DottedList(nodes, tail) =>
+                    (if_or!(debug, "DottedList([", "("), nodes, Some(tail.as_ref()), ")"),
+                value => seq!(Self::write_atom(f, value, debug)?, continue)
+            };
+            f.write_str(open)?;
+            stack.push(Piece::Text(close));
+            if let Some(tail) = tail {
+                stack.extend([Piece::Node(tail), Piece::Text(if_or!(debug, "], ", " . "))]);
+            }
+            for (i, node) in nodes.iter().enumerate().rev() {
+                stack.push(Piece::Node(node));
+                if i > 0 { stack.push(Piece::Text(if_or!(debug, ", ", " "))) }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_atom(f: &mut std::fmt::Formatter<'_>, value: &NodeValue, debug: bool) -> std::fmt::Result {
+        match value {
+            NodeValue::Boolean(b) if debug => write!(f, "Boolean({b:?})"),
+            NodeValue::Boolean(b) => write!(f, "{}", if_or!(*b, "#t", "#f")),
+            NodeValue::Char(ch) if debug => write!(f, "Char({ch:?})"),
+            NodeValue::Char(ch) => write!(f, "{}", escape_char(*ch)),
+            NodeValue::Ignore if debug => write!(f, "Ignore"),
+            NodeValue::Ignore => write!(f, "#ignore"),
+            NodeValue::Inert if debug => write!(f, "Inert"),
+            NodeValue::Inert => write!(f, "#inert"),
+            NodeValue::Number(n) if debug => write!(f, "Number({n:?})"),
+            NodeValue::String(s) if debug => write!(f, "String({s:?})"),
+            NodeValue::Number(n) => write!(f, "{}", n),
+            NodeValue::String(s) => write!(f, "\"{}\"", escape_str(s)),
+            NodeValue::Symbol(symbol) if debug => write!(f, "Symbol({symbol:?})"),
+            NodeValue::Symbol(symbol) => write!(f, "{}", symbol),
+            _ => Ok(())
+        }
+    }
+}
+
+/// Written as the source of the datum, which is read back into the same
+/// node.
 impl Display for Node {
-    // TODO: Ensure the safety of nested call to print lists of arbitrary depth.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Node::List(nodes) => {
-                if nodes.is_empty() { return write!(f, "()"); }
+        Piece::Node(self).write(f, false)
+    }
+}
 
-                if nodes.len() == 1 { return write!(f, "({})", nodes[0]) }
+/// Written as the derived implementation does, but without recursion.
+impl std::fmt::Debug for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Piece::Node(self).write(f, true)
+    }
+}
 
-                write!(f, "({} ", nodes[0])?;
-                for node in nodes[1..nodes.len() - 1].iter() {
-                    write!(f, "{} ", node)?
-                }
-                write!(f, "{})", nodes.last().unwrap())
-            },
-            Node::Number(n) => write!(f, "{}", n),
-            Node::String(s) => write!(f, "{}", s),
-            Node::Symbol(symbol) => write!(f, "{}", symbol)
+impl std::fmt::Debug for NodeValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Piece::Value(self).write(f, true)
+    }
+}
+
+/// The nested nodes are moved to a stack and dropped one by one, instead
+/// of being dropped recursively.
+impl Drop for Node {
+    fn drop(&mut self) {
+        fn detach(value: &mut NodeValue, stack: &mut Vec<Node>) {
+            match value {
+                NodeValue::List(nodes) | NodeValue::Vector(nodes) | NodeValue::Curly(nodes) => stack.append(nodes),
+                NodeValue::DottedList(nodes, tail) => {
+                    stack.append(nodes);
+                    stack.push(core::mem::replace(tail.as_mut(), NodeValue::Inert.into()))
+                },
+                _ => ()
+            }
         }
+        let mut stack = vec![];
+        detach(&mut self.value, &mut stack);
+        while let Some(mut node) = stack.pop() {
+            detach(&mut node.value, &mut stack);
+        }
+    }
+}
+
+impl From<NodeValue> for Node {
+    fn from(value: NodeValue) -> Self {
+        Self { value, span: None }
     }
 }
 
 impl From<&str> for Node {
     fn from(value: &str) -> Self {
-        Self::Symbol(value.into())
+        NodeValue::Symbol(value.into()).into()
     }
 }
 
 impl From<i64> for Node {
     fn from(value: i64) -> Self {
-        Self::Number(value.to_string())
+        NodeValue::Number(value.to_string()).into()
     }
 }
 
-impl Into<Term> for Node {
-    fn into(self) -> Term {
-        match self {
-            Node::List(mut list) => {
-                let mut term = Term::new();
-                term.sub_terms = {
-                    let taken_vec = std::mem::take(&mut list);
-                    taken_vec.into_iter().map(|node| node.into()).collect()
+/// The source location of the node is kept by the term. Curly braces
+/// are rejected, since they have to be transformed before evaluation.
+impl TryFrom<Node> for Term {
+    type Error = Error;
+
+    /// The nodes are converted from an explicit stack of the sequences
+    /// being converted, so that a tree of arbitrary depth can be.
+    fn try_from(node: Node) -> Result<Self, Error> {
+        /// A sequence being converted, the tail of a dotted list is
+        /// converted after the other nodes.
+        struct Frame {
+            vector: bool,
+            nodes: std::vec::IntoIter<Node>,
+            tail: Option<Box<Node>>,
+            dotted: bool,
+            terms: Vec<Term>,
+            span: Option<Span>
+        }
+
+        impl Frame {
+            fn new(vector: bool, nodes: Vec<Node>, tail: Option<Box<Node>>, span: Option<Span>) -> Self {
+                Self { vector, nodes: nodes.into_iter(), dotted: tail.is_some(), tail, terms: vec![], span }
+            }
+
+            fn finish(mut self) -> Term {
+                let term = match (self.vector, self.dotted) {
+                    (true, _) => Term::vector(self.terms),
+                    (_, true) => {
+                        let tail = self.terms.pop().unwrap_or_default();
+                        Term::list_with_tail(self.terms, tail)
+                    },
+                    _ => Term::list(self.terms)
                 };
-                term
-            },
-            Node::Number(n) => {
-                Term::from(n)
+                located(term, self.span)
+            }
+        }
+
+        fn located(term: Term, span: Option<Span>) -> Term {
+            match span {
+                Some(span) => term.with_span(span),
+                None => term
             }
-            Node::String(s) => {
-                Term::from(s)
+        }
+
+        fn invalid(message: String, span: Option<Span>) -> Error {
+            let err = Error::new(ErrorKind::InvalidSyntax).with_message(message);
+            match span {
+                Some(span) => err.with_span(span.range()).with_location(span),
+                None => err
+            }
+        }
+
+        let mut stack: Vec<Frame> = vec![];
+        let (mut node, mut term) = (Some(node), None);
+        loop {
+            match node.take().map(Node::into_parts) {
+                Some((NodeValue::List(nodes), span)) => stack.push(Frame::new(false, nodes, None, span)),
+                Some((NodeValue::Vector(nodes), span)) => stack.push(Frame::new(true, nodes, None, span)),
+                Some((NodeValue::DottedList(nodes, tail), span)) => stack.push(Frame::new(false, nodes, Some(tail), span)),
+                Some((NodeValue::Curly(_), span)) =>
+                    return Err(invalid("Curly braces are reserved for infix expressions.".to_string(), span)),
+                Some((value, span)) => {
+                    let atom = match value {
+                        NodeValue::Number(n) => {
+                            let number = NumberLiteral::parse(&n).map_err(|(_, message)| message)
+                                .and_then(|literal| Number::try_from(literal).map_err(|err| err.message().to_string()));
+                            match number {
+                                Ok(number) => Term::from(number),
+                                Err(message) => return Err(invalid(message, span))
+                            }
+                        },
+                        NodeValue::Boolean(b) => Term::from(b),
+                        NodeValue::Char(ch) => Term::from(ch),
+                        NodeValue::Ignore => Term::ignore(),
+                        NodeValue::String(s) => Term::from(s),
+                        NodeValue::Symbol(symbol) => Term::from(symbol),
+                        // Only `#inert` is left, the sequences are matched above.
+                        _ => Term::inert()
+                    };
+                    term = Some(located(atom, span))
+                },
+                None => ()
+            }
+            let Some(frame) = stack.last_mut() else { break };
+            frame.terms.extend(term.take());
+            node = frame.nodes.next().or_else(|| frame.tail.take().map(|tail| *tail));
+            if node.is_none() {
+                term = stack.pop().map(Frame::finish);
             }
-            Node::Symbol(symbol) => {
-                Term::from(symbol)
-            },
         }
+        Ok(term.unwrap_or_default())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::Token;
-    use super::{Node, Symbol};
+    use crate::parser::{Delimiter, Token};
+    use super::{parse_char, Node, NodeValue, NumberLiteral, Symbol};
 
     #[test]
     fn node_to_string() {
-        use Node::*;
-        assert_eq!(List(vec![Symbol("apply".into()), Symbol("+".into())]).to_string(), "(apply +)");
+        assert_eq!(Node::list(vec!["apply".into(), "+".into()]).to_string(), "(apply +)");
+        assert_eq!(Node::delimited(Delimiter::Bracket, vec![1.into(), 2.into()]).to_string(), "[1 2]");
+        assert_eq!(Node::delimited(Delimiter::Brace, vec![]).to_string(), "{}");
+        let strings = Node::list(vec![NodeValue::String("a b\"\n".into()).into(), NodeValue::Char(' ').into()]);
+        assert_eq!(strings.to_string(), "(\"a b\\\"\\n\" #\\space)");
+        assert_eq!(strings.to_source(), "\"a b\\\"\\n\"\n#\\space\n");
+    }
+
+    #[test]
+    fn node_deep_nesting() {
+        let depth = 1_000_000;
+        let node = Node::list(vec!["a".into(), NodeValue::DottedList(vec![1.into()], Box::new("b".into())).into()]);
+        assert_eq!(format!("{node:?}"), concat!(
+            "Node { value: List([Node { value: Symbol(Symbol(\"a\")), span: None }, ",
+            "Node { value: DottedList([Node { value: Number(\"1\"), span: None }], ",
+            "Node { value: Symbol(Symbol(\"b\")), span: None }), span: None }]), span: None }"
+        ));
+        let nested = (0..depth).fold(Node::from("a"), |node, i| match i % 3 {
+            0 => Node::list(vec![node]),
+            1 => Node::delimited(Delimiter::Bracket, vec![node]),
+            _ => NodeValue::DottedList(vec![node], Box::new("b".into())).into()
+        });
+        let text = nested.to_string();
+        assert!(text.starts_with("(([(") && text.ends_with("] . b))"));
+        assert!(format!("{nested:?}").starts_with("Node { value: List([Node { value: DottedList([Node { value: Vector(["));
+        assert!(format!("{:?}", nested.value()).starts_with("List([Node { value: DottedList(["));
+        let same = (0..depth).fold(Node::from("a"), |node, i| match i % 3 {
+            0 => Node::list(vec![node]),
+            1 => Node::delimited(Delimiter::Bracket, vec![node]),
+            _ => NodeValue::DottedList(vec![node], Box::new("b".into())).into()
+        });
+        assert_eq!(nested, same);
+        let other = (0..depth).fold(Node::from("b"), |node, _| Node::list(vec![node]));
+        assert_ne!(nested, other);
+    }
+
+    #[test]
+    fn node_sequences() {
+        let mut list = Node::list(vec![]);
+        assert_eq!(list.push("a".into()), Some(&mut Node::from("a")));
+        assert_eq!(list.as_ref(), &[Node::from("a")]);
+        assert_eq!(list.pop(), Some("a".into()));
+        let mut atom = Node::from(1);
+        assert_eq!(atom.push("a".into()), None);
+        assert_eq!(atom.pop(), None);
+        assert!(atom.as_ref().is_empty() && atom.as_mut().is_empty());
+    }
+
+    #[test]
+    fn number_literal_parse() {
+        use NumberLiteral::*;
+        let integer = |negative, radix, digits: &str| Integer { negative, radix, digits: digits.to_string() };
+        assert_eq!(NumberLiteral::parse("42"), Ok(integer(false, 10, "42")));
+        assert_eq!(NumberLiteral::parse("-1_000_000"), Ok(integer(true, 10, "1000000")));
+        assert_eq!(NumberLiteral::parse("#xFF_ff"), Ok(integer(false, 16, "FFff")));
+        assert_eq!(NumberLiteral::parse("#b-101"), Ok(integer(true, 2, "101")));
+        assert_eq!(NumberLiteral::parse("#o17"), Ok(integer(false, 8, "17")));
+        assert_eq!(NumberLiteral::parse("+1/3"), Ok(Rational {
+            negative: false, radix: 10, numerator: "1".to_string(), denominator: "3".to_string()
+        }));
+        assert_eq!(NumberLiteral::parse("1.5"), Ok(Decimal("1.5e0".to_string())));
+        assert_eq!(NumberLiteral::parse("-.5e-3"), Ok(Decimal("-0.5e-3".to_string())));
+        assert_eq!(NumberLiteral::parse("2E10"), Ok(Decimal("2.0e+10".to_string())));
+        assert_eq!(NumberLiteral::parse("-inf.0"), Ok(Decimal("-inf".to_string())));
+    }
+
+    #[test]
+    fn number_literal_errors() {
+        let error = |token: &str| NumberLiteral::parse(token).unwrap_err();
+        assert_eq!(error("12a").0, 2..3);
+        assert_eq!(error("#b102").0, 4..5);
+        assert_eq!(error("1__0").0, 1..2);
+        assert_eq!(error("1_").0, 1..2);
+        assert_eq!(error("1/0").1, "The denominator of a rational can't be zero.");
+        assert_eq!(error("1/").0, 1..2);
+        assert_eq!(error("1e").1, "Expected digits in the exponent.");
+        assert_eq!(error("#x1.5").0, 3..4);
+        assert_eq!(error("1.5.2").0, 3..4);
+        assert_eq!(error("#q1").0, 0..2);
+        assert_eq!(error("1+").1, "Unexpected '+' in the number.");
+    }
+
+    #[test]
+    fn number_node_to_term() {
+        use crate::evaluation::Term;
+        assert_eq!(Term::try_from(Node::from(NodeValue::Number("#x1F".into()))).unwrap().to_string(), "31");
+        let number = Node::from(NodeValue::Number("12a".into())).with_span(crate::parser::Span::new("test".into(), 3..6));
+        let err = Term::try_from(Node::list(vec!["f".into(), number])).unwrap_err();
+        assert_eq!(err.kind(), crate::error::ErrorKind::InvalidSyntax);
+        assert_eq!(err.message(), "Invalid digit 'a' for a number in radix 10.");
+        assert_eq!(err.span(), 3..6);
+    }
+
+    #[test]
+    fn number_literal_is_numeric() {
+        for token in ["1", "-1", "+.5", ".5", "#x1F", "+inf.0", "1+"] {
+            assert!(NumberLiteral::is_numeric(token), "{token}");
+        }
+        for token in ["+", "-", "...", "-x", "#t", "a1", "+inf"] {
+            assert!(!NumberLiteral::is_numeric(token), "{token}");
+        }
+    }
+
+    #[test]
+    fn char_literals() {
+        assert_eq!(parse_char("a"), Ok('a'));
+        assert_eq!(parse_char("newline"), Ok('\n'));
+        assert_eq!(parse_char("x3bb"), Ok('λ'));
+        assert_eq!(parse_char("x"), Ok('x'));
+        assert!(parse_char("").is_err());
+        assert!(parse_char("xyz").is_err());
+        use NodeValue::*;
+        assert_eq!(Node::list(vec![Char(' ').into(), Char('a').into(), Boolean(true).into(), Ignore.into()]).to_string(),
+            "(#\\space #\\a #t #ignore)");
     }
 
     #[test]